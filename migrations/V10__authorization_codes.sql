@@ -0,0 +1,9 @@
+ALTER TABLE states ADD COLUMN code_challenge VARCHAR(128);
+
+CREATE TABLE authorization_codes (
+    code VARCHAR(64) PRIMARY KEY NOT NULL,
+    session_id VARCHAR(32) NOT NULL,
+    api_name VARCHAR(64) NOT NULL,
+    code_challenge VARCHAR(128) NOT NULL,
+    expiry BIGINT NOT NULL
+);
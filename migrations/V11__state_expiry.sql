@@ -0,0 +1,3 @@
+ALTER TABLE states ADD COLUMN created_at BIGINT;
+UPDATE states SET created_at = UNIX_TIMESTAMP();
+ALTER TABLE states MODIFY created_at BIGINT NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN invited BOOLEAN NOT NULL DEFAULT false;
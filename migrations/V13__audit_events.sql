@@ -0,0 +1,10 @@
+CREATE TABLE audit_events (
+    id BIGINT PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    timestamp BIGINT NOT NULL,
+    event VARCHAR(32) NOT NULL,
+    user_id VARCHAR(255),
+    api_name VARCHAR(64),
+    details TEXT,
+    INDEX audit_events_user_id (user_id, timestamp),
+    INDEX audit_events_timestamp (timestamp)
+);
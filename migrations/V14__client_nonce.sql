@@ -0,0 +1,2 @@
+ALTER TABLE states ADD COLUMN client_nonce VARCHAR(255);
+ALTER TABLE authorization_codes ADD COLUMN nonce VARCHAR(255);
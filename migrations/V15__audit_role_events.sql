@@ -0,0 +1,2 @@
+UPDATE audit_events SET event = 'user_roles_changed' WHERE event = 'roles_changed';
+UPDATE audit_events SET event = 'role_definition_changed' WHERE event = 'role_changed';
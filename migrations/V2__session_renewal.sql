@@ -0,0 +1,2 @@
+ALTER TABLE sessions ADD COLUMN absolute_expiry BIGINT;
+UPDATE sessions SET absolute_expiry = expiry;
@@ -0,0 +1,7 @@
+ALTER TABLE api_users ADD UNIQUE (name);
+
+CREATE TABLE api_redirect_uris (
+    id INT PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    api_name VARCHAR(64) NOT NULL,
+    uri_prefix TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE states ADD COLUMN api_name VARCHAR(64);
+ALTER TABLE sessions ADD COLUMN api_name VARCHAR(64);
@@ -0,0 +1 @@
+ALTER TABLE api_users ADD COLUMN admin BOOLEAN NOT NULL DEFAULT false;
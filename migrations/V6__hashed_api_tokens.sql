@@ -0,0 +1,5 @@
+ALTER TABLE api_users DROP PRIMARY KEY, ADD PRIMARY KEY (name), MODIFY api_token VARCHAR(64) NULL;
+ALTER TABLE api_users ADD COLUMN token_prefix VARCHAR(8);
+ALTER TABLE api_users ADD COLUMN token_salt VARCHAR(32);
+ALTER TABLE api_users ADD COLUMN token_hash VARCHAR(64);
+CREATE INDEX api_users_token_prefix ON api_users (token_prefix);
@@ -0,0 +1,15 @@
+CREATE TABLE roles (
+    role_name VARCHAR(32) PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE role_scopes (
+    id INT PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    role_name VARCHAR(32) NOT NULL,
+    scope_name VARCHAR(32) NOT NULL
+);
+
+CREATE TABLE user_roles (
+    id INT PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    role_name VARCHAR(32) NOT NULL,
+    user_id VARCHAR(255) NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE access_tokens (
+    user_id VARCHAR(255) PRIMARY KEY NOT NULL,
+    access_token TEXT NOT NULL,
+    expiry BIGINT NOT NULL
+);
@@ -0,0 +1,5 @@
+ALTER TABLE states ADD COLUMN provider VARCHAR(32);
+ALTER TABLE users ADD COLUMN provider VARCHAR(32);
+ALTER TABLE scopes MODIFY user_id VARCHAR(255) NOT NULL;
+UPDATE states SET provider = 'google';
+UPDATE users SET provider = 'google';
@@ -0,0 +1,120 @@
+//! API tokens are stored as a salted SHA-256 hash. The first few characters of the token are
+//! stored in plaintext, so the row belonging to a token can be found without hashing against every row.
+
+use mysql::{prelude::Queryable, Row, Params, params};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use log::info;
+use crate::audit::{self, AuditEvent};
+
+const PREFIX_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+
+pub struct HashedApiToken {
+    pub prefix: String,
+    pub salt:   String,
+    pub hash:   String,
+}
+
+/// Generate a new API token. This uses the same source of randomness as session IDs
+pub fn generate() -> String {
+    rand::thread_rng().sample_iter(rand::distributions::Alphanumeric).take(64).map(char::from).collect()
+}
+
+/// The part of the token that is stored in plaintext, used to look up the token
+pub fn prefix(api_token: &str) -> &str {
+    match api_token.char_indices().nth(PREFIX_LEN) {
+        Some((idx, _)) => &api_token[..idx],
+        None => api_token,
+    }
+}
+
+pub fn hash(api_token: &str) -> HashedApiToken {
+    let salt: Vec<u8> = (0..SALT_LEN).map(|_| rand::thread_rng().gen()).collect();
+    let salt = base64::encode(&salt);
+
+    HashedApiToken {
+        prefix: prefix(api_token).to_string(),
+        hash:   digest(api_token, &salt),
+        salt,
+    }
+}
+
+/// Check if the token matches the stored hash. The comparison is done in constant time
+pub fn verify(api_token: &str, salt: &str, hash: &str) -> bool {
+    digest(api_token, salt).as_bytes().ct_eq(hash.as_bytes()).into()
+}
+
+fn digest(api_token: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(api_token.as_bytes());
+    base64::encode(hasher.finalize())
+}
+
+/// Find the `api_users` row belonging to the token, selecting the requested columns
+pub fn find<Q: Queryable>(conn: &mut Q, api_token: &str, columns: &str) -> mysql::Result<Option<Row>> {
+    let rows: Vec<Row> = conn.exec(format!("SELECT token_salt,token_hash,{} FROM api_users WHERE token_prefix = :prefix", columns), params! {
+        "prefix" => prefix(api_token)
+    })?;
+
+    let row = rows.into_iter()
+        .find(|r| {
+            let salt: String = r.get("token_salt").unwrap();
+            let hash: String = r.get("token_hash").unwrap();
+            verify(api_token, &salt, &hash)
+        });
+
+    Ok(row)
+}
+
+/// Hash tokens that are still stored in plaintext, from before tokens were hashed.
+pub fn migrate_plaintext_tokens<Q: Queryable>(conn: &mut Q) -> mysql::Result<()> {
+    let rows: Vec<Row> = conn.exec("SELECT name,api_token FROM api_users WHERE api_token IS NOT NULL", Params::Empty)?;
+    for row in rows {
+        let name: String = row.get("name").unwrap();
+        let api_token: String = row.get("api_token").unwrap();
+
+        let hashed = hash(&api_token);
+        conn.exec_drop("UPDATE api_users SET api_token = NULL, token_prefix = :prefix, token_salt = :salt, token_hash = :hash WHERE name = :name", params! {
+            "prefix" => &hashed.prefix,
+            "salt" => &hashed.salt,
+            "hash" => &hashed.hash,
+            "name" => &name
+        })?;
+
+        info!("Hashed plaintext API token for '{}'", &name);
+    }
+
+    Ok(())
+}
+
+/// Create an admin API with the provided token, unless an API with admin rights exists already
+pub fn bootstrap_admin<Q: Queryable>(conn: &mut Q, name: &str, api_token: &str) -> anyhow::Result<()> {
+    if conn.exec_first::<Row, &str, Params>("SELECT 1 FROM api_users WHERE admin = true", Params::Empty)?.is_some() {
+        return Ok(());
+    }
+
+    if api_token.len() < 32 {
+        anyhow::bail!("The bootstrap admin token must be at least 32 characters long");
+    }
+
+    if conn.exec_first::<Row, &str, Params>("SELECT 1 FROM api_users WHERE name = :name", params! {
+        "name" => name
+    })?.is_some() {
+        anyhow::bail!("Can not create the bootstrap admin API, an API named '{}' without admin rights exists already", name);
+    }
+
+    let hashed = hash(api_token);
+    conn.exec_drop("INSERT INTO api_users (active, admin, name, token_prefix, token_salt, token_hash) VALUES (true, true, :name, :prefix, :salt, :hash)", params! {
+        "name" => name,
+        "prefix" => &hashed.prefix,
+        "salt" => &hashed.salt,
+        "hash" => &hashed.hash
+    })?;
+
+    audit::record(conn, AuditEvent::ApiChanged, None, None, Some(&format!("created admin API {} from the bootstrap admin token", name)))?;
+    info!("Created admin API '{}' from the bootstrap admin token", name);
+    Ok(())
+}
@@ -1,8 +1,29 @@
 use serde::{Deserialize, Serialize};
+use async_trait::async_trait;
 use anyhow::Result;
+use crate::apis::jwks::Jwks;
+use crate::apis::oidc::DiscoveryDocument;
+use crate::apis::provider::{error_for_status, IdentityClaims, IdentityProvider, RefreshResponse, TokenResponse};
 use crate::env::Env;
+use crate::error::Error;
 
-const GOOGLE_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_DISCOVERY_URL: &str = "https://accounts.google.com/.well-known/openid-configuration";
+/// Google ID tokens can carry either of these issuers, besides the one in the discovery document
+const GOOGLE_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
+const DEFAULT_SCOPES: &str = "openid profile email";
+
+#[derive(Serialize)]
+struct GoogleLoginQuery<'a> {
+    client_id:              &'a str,
+    redirect_uri:           &'a str,
+    response_type:          &'static str,
+    scope:                  &'a str,
+    access_type:            &'static str,
+    state:                  &'a str,
+    include_granted_scopes: bool,
+    promt:                  &'static str,
+    nonce:                  &'a str,
+}
 
 #[derive(Serialize)]
 struct ExchangeGrantTokenRequest<'a> {
@@ -13,33 +34,6 @@ struct ExchangeGrantTokenRequest<'a> {
     redirect_uri:   &'a str
 }
 
-#[derive(Deserialize)]
-pub struct ExchangeGrantTokenResponse {
-    pub access_token:   String,
-    pub expires_in:     u64,
-    pub refresh_token:  Option<String>,
-    pub id_token:       String,
-    pub scope:          String,
-}
-
-pub fn exchange_grant_token(env: &Env, code: &str, redirect_uri: &str) -> Result<ExchangeGrantTokenResponse> {
-    let payload = ExchangeGrantTokenRequest {
-        client_id:      &env.google_client_id,
-        client_secret:  &env.google_client_secret,
-        code,
-        grant_type:     "authorization_code",
-        redirect_uri
-    };
-
-    let response: ExchangeGrantTokenResponse = reqwest::blocking::Client::new()
-        .post(GOOGLE_TOKEN_ENDPOINT)
-        .json(&payload)
-        .send()?
-        .json()?;
-
-    Ok(response)
-}
-
 #[derive(Serialize)]
 struct ExchangeRefreshTokenRequest<'a> {
     client_id:      &'a str,
@@ -49,25 +43,181 @@ struct ExchangeRefreshTokenRequest<'a> {
 }
 
 #[derive(Deserialize)]
-pub struct ExchangeRefreshTokenResponse {
-    pub access_token:   String,
-    pub expires_in:     i64,
-    pub scope:          String,
+struct GoogleClaims {
+    sub:        String,
+    name:       Option<String>,
+    email:      Option<String>,
+    picture:    Option<String>,
+    nonce:      Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    hd:         Option<String>,
+}
+
+pub struct GoogleProvider {
+    client_id:              String,
+    client_secret:          String,
+    /// The issuer from the discovery document. This differs from Google's when pointed at e.g. a mock issuer
+    issuer:                 String,
+    authorization_endpoint: String,
+    token_endpoint:         String,
+    http:                   reqwest::Client,
+    jwks:                   Jwks,
+}
+
+impl GoogleProvider {
+    /// Fetch Google's discovery document, and create the provider from it
+    pub async fn discover(env: &Env, http: reqwest::Client) -> Result<Self> {
+        let discovery_url = env.google_discovery_url.as_deref().unwrap_or(GOOGLE_DISCOVERY_URL);
+        let document = DiscoveryDocument::fetch(discovery_url, &http).await?;
+        let jwks_uri = env.google_jwks_uri.clone().unwrap_or(document.jwks_uri);
+
+        Ok(Self {
+            client_id:              env.google_client_id.clone(),
+            client_secret:          env.google_client_secret.clone(),
+            issuer:                 document.issuer,
+            authorization_endpoint: document.authorization_endpoint,
+            token_endpoint:         document.token_endpoint,
+            jwks:                   Jwks::new(jwks_uri, http.clone()),
+            http,
+        })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for GoogleProvider {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn authorization_url(&self, redirect_uri: &str, state: &str, nonce: &str, requested_scopes: Option<&str>) -> Result<String> {
+        let scopes = if let Some(scopes) = requested_scopes {
+            format!("{} {}", DEFAULT_SCOPES, scopes)
+        } else {
+            DEFAULT_SCOPES.to_string()
+        };
+
+        let google_query_params = GoogleLoginQuery {
+            client_id:              &self.client_id,
+            redirect_uri,
+            response_type:          "code",
+            scope:                  &scopes,
+            access_type:            "offline",
+            state,
+            include_granted_scopes: true,
+            promt:                  "select_account",
+            nonce,
+        };
+
+        let query_params = serde_qs::to_string(&google_query_params)?;
+        Ok(format!("{}?{}", self.authorization_endpoint, query_params))
+    }
+
+    async fn exchange_code(&self, code: &str, redirect_uri: &str) -> Result<TokenResponse> {
+        let payload = ExchangeGrantTokenRequest {
+            client_id:      &self.client_id,
+            client_secret:  &self.client_secret,
+            code,
+            grant_type:     "authorization_code",
+            redirect_uri
+        };
+
+        let response = self.http
+            .post(&self.token_endpoint)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let response: TokenResponse = error_for_status(response).await?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<RefreshResponse> {
+        let payload = ExchangeRefreshTokenRequest {
+            client_id:      &self.client_id,
+            client_secret:  &self.client_secret,
+            grant_type:     "refresh_token",
+            refresh_token
+        };
+
+        let response = self.http
+            .post(&self.token_endpoint)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let response: RefreshResponse = error_for_status(response).await?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Verify an ID token issued by Google against Google's published keys.
+    /// This checks the signature, and that the token was issued by Google, for us, and has not yet expired.
+    async fn verify_id_token(&self, id_token: &str) -> Result<IdentityClaims, Error> {
+        let issuers = [GOOGLE_ISSUERS[0], GOOGLE_ISSUERS[1], self.issuer.as_str()];
+        let claims: GoogleClaims = self.jwks.verify(id_token, &self.client_id, &issuers).await?;
+
+        Ok(IdentityClaims {
+            sub:        claims.sub,
+            name:       claims.name,
+            email:      claims.email,
+            picture:    claims.picture,
+            nonce:      claims.nonce,
+            email_verified: claims.email_verified,
+            hosted_domain:  claims.hd,
+        })
+    }
+
+    /// Google was the only provider before others were supported, so its users are not namespaced
+    fn user_id(&self, sub: &str) -> String {
+        sub.to_string()
+    }
 }
 
-pub fn refresh_token(env: &Env, refresh_token: &str) -> Result<ExchangeRefreshTokenResponse> {
-    let payload = ExchangeRefreshTokenRequest {
-        client_id:      &env.google_client_id,
-        client_secret:  &env.google_client_secret,
-        grant_type:     "refresh_token",
-        refresh_token
-    };
-
-    let response: ExchangeRefreshTokenResponse = reqwest::blocking::Client::new()
-        .post(GOOGLE_TOKEN_ENDPOINT)
-        .json(&payload)
-        .send()?
-        .json()?;
-
-    Ok(response)
+#[cfg(test)]
+mod tests {
+    use super::GoogleProvider;
+    use crate::apis::provider::{IdentityProvider, ProviderError};
+    use crate::tests::mock_issuer::{self, MockIssuer};
+
+    const REDIRECT_URI: &str = "http://authlander.test/oauth2/grant";
+
+    #[actix_web::test]
+    async fn exchanges_code_with_discovered_issuer() {
+        let issuer = MockIssuer::start();
+        let env = crate::tests::env(&[("GOOGLE_DISCOVERY_URL", &issuer.discovery_url())]);
+        let provider = GoogleProvider::discover(&env, reqwest::Client::new()).await.unwrap();
+
+        // The mock issuer sends the user straight back with a code
+        let authorization_url = provider.authorization_url(REDIRECT_URI, "state", "nonce", None).unwrap();
+        let response = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build().unwrap()
+            .get(&authorization_url)
+            .send().await.unwrap();
+        let location = response.headers()["location"].to_str().unwrap();
+        let code = location.strip_prefix(&format!("{}?code=", REDIRECT_URI)).unwrap().split('&').next().unwrap();
+
+        let response = provider.exchange_code(code, REDIRECT_URI).await.unwrap();
+        let claims = provider.verify_id_token(&response.id_token).await.unwrap();
+
+        assert_eq!(claims.sub, mock_issuer::SUB);
+        assert_eq!(claims.email.as_deref(), Some(mock_issuer::EMAIL));
+        assert_eq!(claims.nonce.as_deref(), Some("nonce"));
+    }
+
+    #[actix_web::test]
+    async fn keeps_error_code_of_rejected_exchange() {
+        let issuer = MockIssuer::start();
+        let env = crate::tests::env(&[("GOOGLE_DISCOVERY_URL", &issuer.discovery_url())]);
+        let provider = GoogleProvider::discover(&env, reqwest::Client::new()).await.unwrap();
+
+        let error = provider.exchange_code("unknown", REDIRECT_URI).await.err().unwrap();
+        assert_eq!(error.downcast_ref::<ProviderError>().unwrap().error, "invalid_grant");
+    }
 }
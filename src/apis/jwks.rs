@@ -0,0 +1,145 @@
+use std::sync::RwLock;
+use anyhow::Result;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use serde::de::DeserializeOwned;
+use crate::error::Error;
+
+// 1 hour
+const JWKS_CACHE_TTL_SECS: i64 = 3_600;
+
+struct CachedJwks {
+    keys:       JwkSet,
+    fetched_at: i64,
+}
+
+/// A cached JSON Web Key Set, used to verify the signature of JWTs issued by an identity provider.
+///
+/// The source can either be an HTTP(S) URL, or a path to a local file prefixed with `file://`.
+pub struct Jwks {
+    source: String,
+    http:   reqwest::Client,
+    cache:  RwLock<Option<CachedJwks>>,
+}
+
+impl Jwks {
+    pub fn new<S: Into<String>>(source: S, http: reqwest::Client) -> Self {
+        Self {
+            source: source.into(),
+            http,
+            cache:  RwLock::new(None),
+        }
+    }
+
+    /// Verify the signature and the `iss`, `aud` and `exp` claims of an RS256 signed JWT,
+    /// returning its claims if the token is valid
+    pub async fn verify<T: DeserializeOwned>(&self, token: &str, audience: &str, issuers: &[&str]) -> Result<T, Error> {
+        let header = jsonwebtoken::decode_header(token)?;
+        if header.alg != Algorithm::RS256 {
+            return Err(Error::UnauthorizedMsg("The ID token is not signed with RS256"));
+        }
+
+        let kid = match header.kid {
+            Some(kid) => kid,
+            None => return Err(Error::UnauthorizedMsg("The ID token does not specify a key ID")),
+        };
+
+        let key = match self.find_key(&kid).await? {
+            Some(k) => k,
+            None => return Err(Error::UnauthorizedMsg("The ID token is signed with an unknown key")),
+        };
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[audience]);
+        validation.set_issuer(issuers);
+
+        let token_data = jsonwebtoken::decode::<T>(token, &key, &validation)?;
+        Ok(token_data.claims)
+    }
+
+    /// Find the key with the provided key ID. If the cached key set is stale, or does not contain
+    /// the key, the key set is fetched again.
+    async fn find_key(&self, kid: &str) -> Result<Option<DecodingKey>> {
+        let now = chrono::Utc::now().timestamp();
+
+        {
+            let cache = self.cache.read().unwrap();
+            if let Some(cached) = &*cache {
+                if now - cached.fetched_at < JWKS_CACHE_TTL_SECS {
+                    if let Some(jwk) = cached.keys.find(kid) {
+                        return Ok(Some(DecodingKey::from_jwk(jwk)?));
+                    }
+                }
+            }
+        }
+
+        // Google rotates its keys, so an unknown key ID means our cache might be out of date
+        let keys = self.fetch().await?;
+        let key = match keys.find(kid) {
+            Some(jwk) => Some(DecodingKey::from_jwk(jwk)?),
+            None => None,
+        };
+
+        *self.cache.write().unwrap() = Some(CachedJwks { keys, fetched_at: now });
+        Ok(key)
+    }
+
+    async fn fetch(&self) -> Result<JwkSet> {
+        let keys: JwkSet = match self.source.strip_prefix("file://") {
+            Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+            None => self.http
+                .get(&self.source)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?,
+        };
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use serde_json::{json, Value};
+    use super::Jwks;
+    use crate::error::Error;
+    use crate::tests::{signer, JWKS};
+
+    const AUDIENCE: &str = "authlander";
+    const ISSUER: &str = "https://issuer.example.com";
+
+    fn id_token(aud: &str, iss: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        signer().sign("JWT", &json!({
+            "iss": iss,
+            "sub": "1234",
+            "aud": aud,
+            "iat": now,
+            "exp": now + 60,
+        })).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn verifies_token_signed_with_local_key() {
+        let jwks = Jwks::new(JWKS, reqwest::Client::new());
+        let claims: HashMap<String, Value> = jwks.verify(&id_token(AUDIENCE, ISSUER), AUDIENCE, &[ISSUER]).await.unwrap();
+        assert_eq!(claims["sub"], "1234");
+    }
+
+    #[actix_web::test]
+    async fn rejects_wrong_audience() {
+        let jwks = Jwks::new(JWKS, reqwest::Client::new());
+        let result = jwks.verify::<HashMap<String, Value>>(&id_token("another-client", ISSUER), AUDIENCE, &[ISSUER]).await;
+        assert!(matches!(result, Err(Error::Jwt(_))));
+    }
+
+    #[actix_web::test]
+    async fn rejects_wrong_issuer() {
+        let jwks = Jwks::new(JWKS, reqwest::Client::new());
+        let result = jwks.verify::<HashMap<String, Value>>(&id_token(AUDIENCE, "https://evil.example.com"), AUDIENCE, &[ISSUER]).await;
+        assert!(matches!(result, Err(Error::Jwt(_))));
+    }
+}
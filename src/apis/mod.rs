@@ -1 +1,4 @@
-pub mod google_auth;
\ No newline at end of file
+pub mod google_auth;
+pub mod jwks;
+pub mod oidc;
+pub mod provider;
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use async_trait::async_trait;
+use anyhow::Result;
+use crate::apis::jwks::Jwks;
+use crate::apis::provider::{error_for_status, IdentityClaims, IdentityProvider, RefreshResponse, TokenResponse};
+use crate::error::Error;
+
+const DEFAULT_SCOPES: &str = "openid profile email";
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// The configuration of a generic OpenID Connect provider, as read from the identity providers config file
+#[derive(Deserialize)]
+pub struct OidcProviderConfig {
+    pub name:           String,
+    /// The URL of the provider's `/.well-known/openid-configuration` document
+    pub discovery_url:  String,
+    pub client_id:      String,
+    pub client_secret:  String,
+    /// The scopes to request. Defaults to `openid profile email`
+    pub scopes:         Option<String>,
+    /// The names of the ID token claims to map, for providers using non-standard claim names
+    #[serde(default)]
+    pub claims:         ClaimNames,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ClaimNames {
+    pub name:           String,
+    pub email:          String,
+    pub picture:        String,
+    pub hosted_domain:  String,
+}
+
+impl Default for ClaimNames {
+    fn default() -> Self {
+        Self {
+            name:           "name".to_string(),
+            email:          "email".to_string(),
+            picture:        "picture".to_string(),
+            hosted_domain:  "hd".to_string(),
+        }
+    }
+}
+
+/// The fields we need from an OpenID Connect discovery document
+#[derive(Deserialize)]
+pub struct DiscoveryDocument {
+    pub issuer:                 String,
+    pub authorization_endpoint: String,
+    pub token_endpoint:         String,
+    pub jwks_uri:               String,
+}
+
+impl DiscoveryDocument {
+    /// Fetch the discovery document from an issuer's `/.well-known/openid-configuration` URL
+    pub async fn fetch(discovery_url: &str, http: &reqwest::Client) -> Result<Self> {
+        let document: Self = http
+            .get(discovery_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // The issuer must be the URL the discovery document was published under, otherwise the document could be used to impersonate another issuer
+        if !issuer_matches(discovery_url, &document.issuer) {
+            anyhow::bail!("The issuer '{}' does not match the discovery URL '{}'", document.issuer, discovery_url);
+        }
+
+        Ok(document)
+    }
+}
+
+/// The discovery URL must be exactly the issuer followed by `/.well-known/openid-configuration`, as per OpenID Connect Discovery section 4.3
+fn issuer_matches(discovery_url: &str, issuer: &str) -> bool {
+    discovery_url.strip_suffix(DISCOVERY_PATH) == Some(issuer.trim_end_matches('/'))
+}
+
+#[derive(Serialize)]
+struct AuthorizationQuery<'a> {
+    client_id:      &'a str,
+    redirect_uri:   &'a str,
+    response_type:  &'static str,
+    scope:          &'a str,
+    state:          &'a str,
+    nonce:          &'a str,
+}
+
+#[derive(Serialize)]
+struct ExchangeCodeRequest<'a> {
+    client_id:      &'a str,
+    client_secret:  &'a str,
+    code:           &'a str,
+    grant_type:     &'static str,
+    redirect_uri:   &'a str,
+}
+
+#[derive(Serialize)]
+struct RefreshTokenRequest<'a> {
+    client_id:      &'a str,
+    client_secret:  &'a str,
+    grant_type:     &'static str,
+    refresh_token:  &'a str,
+}
+
+/// A generic OpenID Connect provider, configured from its discovery document
+pub struct OidcProvider {
+    config:                 OidcProviderConfig,
+    issuer:                 String,
+    authorization_endpoint: String,
+    token_endpoint:         String,
+    http:                   reqwest::Client,
+    jwks:                   Jwks,
+}
+
+impl OidcProvider {
+    /// Fetch the provider's discovery document, and create the provider from it
+    pub async fn discover(config: OidcProviderConfig, http: reqwest::Client) -> Result<Self> {
+        let document = DiscoveryDocument::fetch(&config.discovery_url, &http).await?;
+
+        Ok(Self {
+            config,
+            issuer:                 document.issuer,
+            authorization_endpoint: document.authorization_endpoint,
+            token_endpoint:         document.token_endpoint,
+            jwks:                   Jwks::new(document.jwks_uri, http.clone()),
+            http,
+        })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OidcProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn authorization_url(&self, redirect_uri: &str, state: &str, nonce: &str, requested_scopes: Option<&str>) -> Result<String> {
+        let default_scopes = self.config.scopes.as_deref().unwrap_or(DEFAULT_SCOPES);
+        let scopes = if let Some(scopes) = requested_scopes {
+            format!("{} {}", default_scopes, scopes)
+        } else {
+            default_scopes.to_string()
+        };
+
+        let query = AuthorizationQuery {
+            client_id:      &self.config.client_id,
+            redirect_uri,
+            response_type:  "code",
+            scope:          &scopes,
+            state,
+            nonce,
+        };
+
+        // The authorization endpoint is allowed to contain a query component of its own
+        let separator = if self.authorization_endpoint.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", self.authorization_endpoint, separator, serde_qs::to_string(&query)?))
+    }
+
+    async fn exchange_code(&self, code: &str, redirect_uri: &str) -> Result<TokenResponse> {
+        let payload = ExchangeCodeRequest {
+            client_id:      &self.config.client_id,
+            client_secret:  &self.config.client_secret,
+            code,
+            grant_type:     "authorization_code",
+            redirect_uri,
+        };
+
+        let response = self.http
+            .post(&self.token_endpoint)
+            .form(&payload)
+            .send()
+            .await?;
+
+        let response: TokenResponse = error_for_status(response).await?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<RefreshResponse> {
+        let payload = RefreshTokenRequest {
+            client_id:      &self.config.client_id,
+            client_secret:  &self.config.client_secret,
+            grant_type:     "refresh_token",
+            refresh_token,
+        };
+
+        let response = self.http
+            .post(&self.token_endpoint)
+            .form(&payload)
+            .send()
+            .await?;
+
+        let response: RefreshResponse = error_for_status(response).await?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn verify_id_token(&self, id_token: &str) -> Result<IdentityClaims, Error> {
+        let claims: HashMap<String, serde_json::Value> = self.jwks.verify(id_token, &self.config.client_id, &[self.issuer.as_str()]).await?;
+        let string_claim = |name: &str| claims.get(name).and_then(|v| v.as_str()).map(String::from);
+
+        let sub = match string_claim("sub") {
+            Some(sub) => sub,
+            None => return Err(Error::UnauthorizedMsg("The ID token does not contain a subject")),
+        };
+
+        Ok(IdentityClaims {
+            sub,
+            name:       string_claim(&self.config.claims.name),
+            email:      string_claim(&self.config.claims.email),
+            picture:    string_claim(&self.config.claims.picture),
+            nonce:      string_claim("nonce"),
+            // Some providers send this as a string rather than a boolean
+            email_verified: match claims.get("email_verified") {
+                Some(serde_json::Value::Bool(b)) => *b,
+                Some(serde_json::Value::String(s)) => s == "true",
+                _ => false,
+            },
+            hosted_domain:  string_claim(&self.config.claims.hosted_domain),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::issuer_matches;
+
+    #[test]
+    fn issuer_must_equal_discovery_url() {
+        assert!(issuer_matches("https://accounts.google.com/.well-known/openid-configuration", "https://accounts.google.com"));
+        assert!(issuer_matches("https://example.com/tenant/.well-known/openid-configuration", "https://example.com/tenant/"));
+        assert!(!issuer_matches("https://accounts.google.com/.well-known/openid-configuration", "https://accounts.google.co"));
+        assert!(!issuer_matches("https://example.com/tenant/.well-known/openid-configuration", "https://example.com"));
+    }
+}
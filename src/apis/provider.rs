@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use anyhow::Result;
+use crate::error::Error;
+
+/// The name of the provider used when a login does not specify one
+pub const DEFAULT_PROVIDER: &str = "google";
+
+/// The claims about a user we need from an identity provider, mapped from its ID token
+pub struct IdentityClaims {
+    pub sub:        String,
+    pub name:       Option<String>,
+    pub email:      Option<String>,
+    pub picture:    Option<String>,
+    pub nonce:      Option<String>,
+    pub email_verified: bool,
+    /// The organization the user belongs to, e.g. the Google Workspace domain
+    pub hosted_domain:  Option<String>,
+}
+
+/// An error response from an identity provider's token endpoint, as per RFC 6749 section 5.2
+#[derive(Debug, Deserialize, thiserror::Error)]
+#[error("The identity provider returned '{error}'")]
+pub struct ProviderError {
+    /// The error code, e.g. `invalid_grant`
+    pub error:              String,
+    pub error_description:  Option<String>,
+}
+
+/// Turn an error response from an identity provider's token endpoint into a `ProviderError`,
+/// so the error code the provider returned is not lost in a failure to decode the response
+pub async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    match response.json::<ProviderError>().await {
+        Ok(e) => Err(e.into()),
+        Err(_) => Err(anyhow::anyhow!("The identity provider responded with status {}", status)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TokenResponse {
+    pub access_token:   String,
+    pub expires_in:     i64,
+    pub refresh_token:  Option<String>,
+    pub id_token:       String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshResponse {
+    pub access_token:   String,
+    pub expires_in:     i64,
+}
+
+/// An OAuth2 / OpenID Connect identity provider users can log in with
+#[async_trait]
+pub trait IdentityProvider: Send + Sync {
+    /// The name of the provider, as used in the `provider` parameter of `/oauth2/login`
+    fn name(&self) -> &str;
+
+    /// The URL the user should be sent to, to log in with the provider
+    fn authorization_url(&self, redirect_uri: &str, state: &str, nonce: &str, requested_scopes: Option<&str>) -> Result<String>;
+
+    /// Exchange an authorization code for an access-, refresh- and ID token
+    async fn exchange_code(&self, code: &str, redirect_uri: &str) -> Result<TokenResponse>;
+
+    /// Exchange a refresh token for a new access token
+    async fn refresh_token(&self, refresh_token: &str) -> Result<RefreshResponse>;
+
+    /// Verify the ID token issued by the provider, and map its claims
+    async fn verify_id_token(&self, id_token: &str) -> Result<IdentityClaims, Error>;
+
+    /// The ID under which a user of this provider is stored.
+    /// Subjects are only unique per provider, so they are namespaced by the provider's name
+    fn user_id(&self, sub: &str) -> String {
+        format!("{}:{}", self.name(), sub)
+    }
+}
@@ -0,0 +1,63 @@
+//! A durable record of authentication and administrative events. Events are written in the same
+//! transaction as the change they describe, so every change that happened has a matching event.
+
+use mysql::{prelude::Queryable, params};
+
+#[derive(Clone, Copy)]
+pub enum AuditEvent {
+    LoginStarted,
+    GrantSucceeded,
+    GrantFailed,
+    SessionCreated,
+    SessionExpired,
+    SessionRevoked,
+    TokenIssued,
+    UserCreated,
+    UserInvited,
+    InvitationAccepted,
+    UserActivated,
+    UserDeactivated,
+    UserDeleted,
+    ScopesChanged,
+    UserRolesChanged,
+    RoleDefinitionChanged,
+    ApiChanged,
+}
+
+impl AuditEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoginStarted => "login_started",
+            Self::GrantSucceeded => "grant_succeeded",
+            Self::GrantFailed => "grant_failed",
+            Self::SessionCreated => "session_created",
+            Self::SessionExpired => "session_expired",
+            Self::SessionRevoked => "session_revoked",
+            Self::TokenIssued => "token_issued",
+            Self::UserCreated => "user_created",
+            Self::UserInvited => "user_invited",
+            Self::InvitationAccepted => "invitation_accepted",
+            Self::UserActivated => "user_activated",
+            Self::UserDeactivated => "user_deactivated",
+            Self::UserDeleted => "user_deleted",
+            Self::ScopesChanged => "scopes_changed",
+            Self::UserRolesChanged => "user_roles_changed",
+            Self::RoleDefinitionChanged => "role_definition_changed",
+            Self::ApiChanged => "api_changed",
+        }
+    }
+}
+
+/// Write an audit event.
+///
+/// `user_id` is the user the event is about, `api_name` the API on whose behalf it happened.
+/// Session IDs and tokens are secrets, and must never be put in `details`
+pub fn record<Q: Queryable>(conn: &mut Q, event: AuditEvent, user_id: Option<&str>, api_name: Option<&str>, details: Option<&str>) -> mysql::Result<()> {
+    conn.exec_drop("INSERT INTO audit_events (timestamp, event, user_id, api_name, details) VALUES (:timestamp, :event, :user_id, :api_name, :details)", params! {
+        "timestamp" => chrono::Utc::now().timestamp(),
+        "event" => event.as_str(),
+        "user_id" => user_id,
+        "api_name" => api_name,
+        "details" => details
+    })
+}
@@ -0,0 +1,21 @@
+//! The MySQL connection pool, instrumented so time spent waiting for a connection shows up in the metrics
+
+use mysql::PooledConn;
+use prometheus::Histogram;
+
+pub struct Pool {
+    pool:   mysql::Pool,
+    wait:   Histogram,
+}
+
+impl Pool {
+    pub fn new(pool: mysql::Pool, wait: Histogram) -> Self {
+        Self { pool, wait }
+    }
+
+    /// Get a connection from the pool. The time taken is recorded, which includes connecting if no idle connection was available
+    pub fn get_conn(&self) -> mysql::Result<PooledConn> {
+        let _timer = self.wait.start_timer();
+        self.pool.get_conn()
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use serde::{Serialize, Deserialize};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[derive(Deserialize)]
+pub struct CreateRequest {
+    name:   String,
+    /// The URIs logins for this API may return to must start with one of these prefixes
+    #[serde(default)]
+    redirect_uri_prefixes:  Vec<String>,
+}
+
+#[derive(Serialize)]
+struct CreateResponse {
+    name:       String,
+    api_token:  String,
+}
+
+#[post("/api/create")]
+pub async fn create(data: web::Data<Arc<AppData>>, req: HttpRequest, payload: web::Json<CreateRequest>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(Error::BadRequest("The name must be between 1 and 64 characters long"));
+    }
+
+    for prefix in &payload.redirect_uri_prefixes {
+        super::validate_redirect_uri_prefix(prefix)?;
+    }
+
+    let payload = payload.into_inner();
+    let response = run_blocking(&data, move |data| create_api(data, &caller, payload)).await?;
+
+    // Only the hash is stored, this is the only time the token is returned in plaintext
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn create_api(data: &AppData, caller: &str, payload: CreateRequest) -> Result<CreateResponse, Error> {
+    let CreateRequest { name, redirect_uri_prefixes } = payload;
+    if super::api_exists(data, &name)? {
+        return Err(Error::Conflict("An API with this name already exists"));
+    }
+
+    let api_token = crate::api_token::generate();
+    let hashed = crate::api_token::hash(&api_token);
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("INSERT INTO api_users (active, name, token_prefix, token_salt, token_hash) VALUES (true, :name, :prefix, :salt, :hash)", params! {
+        "name" => &name,
+        "prefix" => &hashed.prefix,
+        "salt" => &hashed.salt,
+        "hash" => &hashed.hash
+    })?;
+
+    tx.exec_batch("INSERT INTO api_redirect_uris (api_name, uri_prefix) VALUES (:api_name, :uri_prefix)", redirect_uri_prefixes.iter().map(|prefix| params! {
+        "api_name" => &name,
+        "uri_prefix" => prefix
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::ApiChanged, None, Some(caller), Some(&format!("created {} with redirect URI prefixes [{}]", name, redirect_uri_prefixes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(CreateResponse { name, api_token })
+}
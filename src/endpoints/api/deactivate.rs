@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[post("/api/deactivate/{api_name}")]
+pub async fn deactivate(data: web::Data<Arc<AppData>>, req: HttpRequest, api_name: web::Path<String>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let api_name = api_name.into_inner();
+
+    run_blocking(&data, move |data| deactivate_api(data, &caller, &api_name)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn deactivate_api(data: &AppData, caller: &str, api_name: &str) -> Result<(), Error> {
+    if !super::api_exists(data, api_name)? {
+        return Err(Error::NotFound("The requested API does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("UPDATE api_users SET active = false WHERE name = :name", params! {
+        "name" => api_name
+    })?;
+
+    audit::record(&mut tx, AuditEvent::ApiChanged, None, Some(caller), Some(&format!("deactivated {}", api_name)))?;
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::check_admin_token;
+
+#[derive(Serialize)]
+struct Response {
+    apis:   Vec<Api>,
+}
+
+#[derive(Serialize)]
+struct Api {
+    name:   String,
+    active: bool,
+    admin:  bool,
+    redirect_uri_prefixes:  Vec<String>,
+}
+
+#[get("/api/list")]
+pub async fn list(data: web::Data<Arc<AppData>>, req: HttpRequest) -> HttpResult {
+    check_admin_token!(req, data);
+    let apis = run_blocking(&data, get_apis).await?;
+    Ok(HttpResponse::Ok().json(&Response { apis }))
+}
+
+fn get_apis(data: &AppData) -> Result<Vec<Api>, Error> {
+    let mut conn = data.pool.get_conn()?;
+
+    let mut prefixes: HashMap<String, Vec<String>> = HashMap::new();
+    let rows: Vec<(String, String)> = conn.exec("SELECT api_name,uri_prefix FROM api_redirect_uris ORDER BY uri_prefix", Params::Empty)?;
+    for (api_name, prefix) in rows {
+        prefixes.entry(api_name).or_default().push(prefix);
+    }
+
+    let rows: Vec<Row> = conn.exec("SELECT name,active,admin FROM api_users", Params::Empty)?;
+    let apis = rows.into_iter()
+        .map(|r| {
+            let name: String = r.get("name").unwrap();
+            Api {
+                redirect_uri_prefixes:  prefixes.remove(&name).unwrap_or_default(),
+                name,
+                active: r.get::<Option<bool>, &str>("active").unwrap().unwrap_or(false),
+                admin:  r.get("admin").unwrap(),
+            }
+        })
+        .collect();
+
+    Ok(apis)
+}
@@ -0,0 +1,27 @@
+use crate::env::AppData;
+use crate::error::Error;
+use mysql::{prelude::Queryable, Row, params};
+
+pub mod create;
+pub mod list;
+pub mod rename;
+pub mod deactivate;
+pub mod rotate;
+pub mod redirect_uris;
+
+fn api_exists(data: &AppData, api_name: &str) -> anyhow::Result<bool> {
+    let mut conn = data.pool.get_conn()?;
+    let row: Option<Row> = conn.exec_first("SELECT 1 FROM api_users WHERE name = :name", params! {
+        "name" => api_name
+    })?;
+
+    Ok(row.is_some())
+}
+
+/// A redirect URI prefix must be an absolute HTTP(S) URL. A prefix like `https://` would allow returning to any host
+fn validate_redirect_uri_prefix(prefix: &str) -> Result<(), Error> {
+    match prefix.strip_prefix("https://").or_else(|| prefix.strip_prefix("http://")) {
+        Some(rest) if !rest.is_empty() && !rest.starts_with('/') => Ok(()),
+        _ => Err(Error::BadRequest("A redirect URI prefix must be an absolute http:// or https:// URL")),
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[derive(Deserialize)]
+pub struct SetRequest {
+    redirect_uri_prefixes:  Vec<String>,
+}
+
+/// Replace the full set of redirect URI prefixes of an API.
+/// Logins for the API may only return to URIs starting with one of them
+#[post("/api/redirect_uris/{api_name}")]
+pub async fn set(data: web::Data<Arc<AppData>>, req: HttpRequest, api_name: web::Path<String>, payload: web::Json<SetRequest>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    for prefix in &payload.redirect_uri_prefixes {
+        super::validate_redirect_uri_prefix(prefix)?;
+    }
+
+    let mut prefixes = payload.into_inner().redirect_uri_prefixes;
+    prefixes.sort();
+    prefixes.dedup();
+
+    let api_name = api_name.into_inner();
+    run_blocking(&data, move |data| set_redirect_uris(data, &caller, &api_name, &prefixes)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn set_redirect_uris(data: &AppData, caller: &str, api_name: &str, prefixes: &[String]) -> Result<(), Error> {
+    if !super::api_exists(data, api_name)? {
+        return Err(Error::NotFound("The requested API does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("DELETE FROM api_redirect_uris WHERE api_name = :api_name", params! {
+        "api_name" => api_name
+    })?;
+
+    tx.exec_batch("INSERT INTO api_redirect_uris (api_name, uri_prefix) VALUES (:api_name, :uri_prefix)", prefixes.iter().map(|prefix| params! {
+        "api_name" => api_name,
+        "uri_prefix" => prefix
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::ApiChanged, None, Some(caller), Some(&format!("set the redirect URI prefixes of {} to [{}]", api_name, prefixes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    name:   String,
+}
+
+#[post("/api/rename/{api_name}")]
+pub async fn rename(data: web::Data<Arc<AppData>>, req: HttpRequest, api_name: web::Path<String>, payload: web::Json<RenameRequest>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(Error::BadRequest("The name must be between 1 and 64 characters long"));
+    }
+
+    let api_name = api_name.into_inner();
+    run_blocking(&data, move |data| rename_api(data, &caller, &api_name, &payload.name)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn rename_api(data: &AppData, caller: &str, api_name: &str, new_name: &str) -> Result<(), Error> {
+    if !super::api_exists(data, api_name)? {
+        return Err(Error::NotFound("The requested API does not exist"));
+    }
+
+    if super::api_exists(data, new_name)? {
+        return Err(Error::Conflict("An API with this name already exists"));
+    }
+
+    // Other tables refer to the API by its name, so they have to be renamed along with it
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for query in [
+        "UPDATE api_users SET name = :new_name WHERE name = :name",
+        "UPDATE api_redirect_uris SET api_name = :new_name WHERE api_name = :name",
+        "UPDATE states SET api_name = :new_name WHERE api_name = :name",
+        "UPDATE sessions SET api_name = :new_name WHERE api_name = :name",
+        "UPDATE authorization_codes SET api_name = :new_name WHERE api_name = :name",
+    ] {
+        tx.exec_drop(query, params! {
+            "new_name" => new_name,
+            "name" => api_name
+        })?;
+    }
+
+    audit::record(&mut tx, AuditEvent::ApiChanged, None, Some(caller), Some(&format!("renamed {} to {}", api_name, new_name)))?;
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[derive(Serialize)]
+struct RotateResponse {
+    name:       String,
+    api_token:  String,
+}
+
+#[post("/api/rotate/{api_name}")]
+pub async fn rotate(data: web::Data<Arc<AppData>>, req: HttpRequest, api_name: web::Path<String>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let api_name = api_name.into_inner();
+
+    let response = run_blocking(&data, move |data| rotate_token(data, &caller, api_name)).await?;
+
+    // Only the hash is stored, this is the only time the new token is returned in plaintext
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn rotate_token(data: &AppData, caller: &str, api_name: String) -> Result<RotateResponse, Error> {
+    if !super::api_exists(data, &api_name)? {
+        return Err(Error::NotFound("The requested API does not exist"));
+    }
+
+    let api_token = crate::api_token::generate();
+    let hashed = crate::api_token::hash(&api_token);
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("UPDATE api_users SET token_prefix = :prefix, token_salt = :salt, token_hash = :hash WHERE name = :name", params! {
+        "prefix" => &hashed.prefix,
+        "salt" => &hashed.salt,
+        "hash" => &hashed.hash,
+        "name" => &api_name
+    })?;
+
+    audit::record(&mut tx, AuditEvent::ApiChanged, None, Some(caller), Some(&format!("rotated the token of {}", api_name)))?;
+    tx.commit()?;
+    Ok(RotateResponse { name: api_name, api_token })
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params, Value};
+use serde::{Serialize, Deserialize};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::check_admin_token;
+
+const DEFAULT_LIMIT: u64 = 100;
+const MAX_LIMIT: u64 = 1000;
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    user_id:    Option<String>,
+    /// Only include events at or after this UNIX timestamp
+    from:       Option<i64>,
+    /// Only include events before this UNIX timestamp
+    to:         Option<i64>,
+    #[serde(default)]
+    offset:     u64,
+    /// The maximum amount of events to return. Defaults to 100, and is capped at 1000
+    limit:      Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Response {
+    events: Vec<Event>,
+}
+
+#[derive(Serialize)]
+struct Event {
+    id:         u64,
+    timestamp:  i64,
+    event:      String,
+    user_id:    Option<String>,
+    api_name:   Option<String>,
+    details:    Option<String>,
+}
+
+/// List audit events, newest first. Only admin APIs may read the audit log, as it covers every API
+#[get("/audit/list")]
+pub async fn list(data: web::Data<Arc<AppData>>, req: HttpRequest, query: web::Query<ListQuery>) -> HttpResult {
+    check_admin_token!(req, data);
+    let query = query.into_inner();
+
+    let events = run_blocking(&data, move |data| get_events(data, &query)).await?;
+    Ok(HttpResponse::Ok().json(&Response { events }))
+}
+
+fn get_events(data: &AppData, query: &ListQuery) -> Result<Vec<Event>, Error> {
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<(&str, Value)> = Vec::new();
+
+    if let Some(user_id) = &query.user_id {
+        conditions.push("user_id = :user_id");
+        params.push(("user_id", user_id.as_str().into()));
+    }
+
+    if let Some(from) = query.from {
+        conditions.push("timestamp >= :from");
+        params.push(("from", from.into()));
+    }
+
+    if let Some(to) = query.to {
+        conditions.push("timestamp < :to");
+        params.push(("to", to.into()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    params.push(("limit", limit.into()));
+    params.push(("offset", query.offset.into()));
+
+    let mut conn = data.pool.get_conn()?;
+    let rows: Vec<Row> = conn.exec(
+        format!("SELECT id,timestamp,event,user_id,api_name,details FROM audit_events {} ORDER BY timestamp DESC, id DESC LIMIT :limit OFFSET :offset", where_clause),
+        Params::from(params)
+    )?;
+
+    let events = rows.into_iter()
+        .map(|row| Event {
+            id:         row.get("id").unwrap(),
+            timestamp:  row.get("timestamp").unwrap(),
+            event:      row.get("event").unwrap(),
+            user_id:    row.get("user_id").unwrap(),
+            api_name:   row.get("api_name").unwrap(),
+            details:    row.get("details").unwrap(),
+        })
+        .collect();
+
+    Ok(events)
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use actix_web::{get, web, HttpResponse};
+use mysql::{prelude::Queryable, params};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+
+#[get("/metrics")]
+pub async fn get(data: web::Data<Arc<AppData>>) -> HttpResult {
+    run_blocking(&data, update_gauges).await?;
+
+    let body = data.metrics.encode()?;
+    Ok(HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(body))
+}
+
+/// The gauges reflect the state of the database, so they are updated whenever the metrics are scraped
+fn update_gauges(data: &AppData) -> Result<(), Error> {
+    let mut conn = data.pool.get_conn()?;
+    let now = chrono::Utc::now().timestamp();
+
+    let active_sessions: i64 = conn.exec_first("SELECT COUNT(*) FROM sessions WHERE expiry > :now AND (absolute_expiry IS NULL OR absolute_expiry > :now)", params! {
+        "now" => now
+    })?.unwrap_or(0);
+
+    let pending_states: i64 = conn.exec_first("SELECT COUNT(*) FROM states WHERE created_at > :created_after", params! {
+        "created_after" => now - data.env.state_ttl_secs
+    })?.unwrap_or(0);
+
+    data.metrics.active_sessions.set(active_sessions);
+    data.metrics.pending_states.set(pending_states);
+    Ok(())
+}
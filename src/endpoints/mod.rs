@@ -1,18 +1,44 @@
+use std::sync::Arc;
+use actix_web::web;
 use crate::env::AppData;
+use crate::error::Error;
 use mysql::{prelude::Queryable, Row, Params, params};
+use serde::Serialize;
 
+pub mod api;
+pub mod audit;
+pub mod metrics;
 pub mod oauth2;
+pub mod role;
+pub mod scope;
 pub mod token;
 pub mod session;
 pub mod user;
+pub mod well_known;
 
+/// Check the API token in the request, evaluating to the name of the API it belongs to
 #[macro_export]
 macro_rules! check_token {
     ($req:expr, $data:expr) => {
         {
-            match crate::endpoints::validate_access_token(&$data, &$crate::authorization!($req))? {
-                true => {},
-                false => return Err($crate::error::Error::Unauthorized),
+            let access_token = $crate::authorization!($req);
+            match $crate::endpoints::run_blocking(&$data, move |data| Ok($crate::endpoints::get_api_name(data, &access_token)?)).await? {
+                Some(api_name) => api_name,
+                None => return Err($crate::error::Error::Unauthorized),
+            }
+        }
+    }
+}
+
+/// Check that the API token in the request has admin rights, evaluating to the name of the API it belongs to
+#[macro_export]
+macro_rules! check_admin_token {
+    ($req:expr, $data:expr) => {
+        {
+            let access_token = $crate::authorization!($req);
+            match $crate::endpoints::run_blocking(&$data, move |data| Ok($crate::endpoints::get_admin_api_name(data, &access_token)?)).await? {
+                Some(api_name) => api_name,
+                None => return Err($crate::error::Error::Unauthorized),
             }
         }
     }
@@ -39,32 +65,102 @@ macro_rules! authorization {
     }
 }
 
-pub fn validate_access_token<S: AsRef<str>>(data: &AppData, api_token: S) -> anyhow::Result<bool> {
+/// Run blocking work, i.e. anything talking to the database, on the blocking thread pool.
+/// This way a slow query does not stall the actix worker, and with it every other request on that worker.
+pub async fn run_blocking<F, T>(data: &web::Data<Arc<AppData>>, f: F) -> Result<T, Error>
+where
+    F: FnOnce(&AppData) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    let data = data.clone();
+    web::block(move || f(&data)).await?
+}
+
+/// Get the name of the API the token belongs to, if it is an active API with admin rights
+pub fn get_admin_api_name<S: AsRef<str>>(data: &AppData, api_token: S) -> anyhow::Result<Option<String>> {
     let mut conn = data.pool.get_conn()?;
 
-    match conn.exec_first::<Row, &str, Params>("SELECT active FROM api_users WHERE api_token = :api_token", params! {
-        "api_token" => api_token.as_ref()
-    })? {
+    match crate::api_token::find(&mut conn, api_token.as_ref(), "active,admin,name")? {
         Some(row) => {
-            let active: bool = row.get("active").unwrap();
-            Ok(active)
+            let active: Option<bool> = row.get("active").unwrap();
+            let admin: bool = row.get("admin").unwrap();
+            if active.unwrap_or(false) && admin {
+                Ok(Some(row.get("name").unwrap()))
+            } else {
+                Ok(None)
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+/// Get the name of the API the token belongs to. Returns `None` if the token does not exist or is not active
+pub fn get_api_name<S: AsRef<str>>(data: &AppData, api_token: S) -> anyhow::Result<Option<String>> {
+    let mut conn = data.pool.get_conn()?;
+
+    match crate::api_token::find(&mut conn, api_token.as_ref(), "active,name")? {
+        Some(row) => {
+            let active: Option<bool> = row.get("active").unwrap();
+            if active.unwrap_or(false) {
+                Ok(Some(row.get("name").unwrap()))
+            } else {
+                Ok(None)
+            }
         },
         None=> {
-            Ok(false)
+            Ok(None)
         }
     }
 }
 
+/// Where a user got a scope from. If `role` is `None`, the scope was granted to the user directly
+#[derive(Serialize)]
+pub struct ScopeSource {
+    pub scope:  String,
+    pub role:   Option<String>,
+}
+
+/// Get the effective scopes of a user, i.e. the scopes granted directly and through roles
 pub fn get_scopes<S: AsRef<str>>(data: &AppData, user_id: S) -> anyhow::Result<Vec<String>> {
+    let mut scopes: Vec<String> = get_scope_sources(data, user_id)?.into_iter()
+        .map(|s| s.scope)
+        .collect();
+
+    scopes.sort();
+    scopes.dedup();
+
+    Ok(scopes)
+}
+
+/// Get every scope of a user, along with where the user got it from.
+/// A scope can occur more than once, if it is granted through multiple sources
+pub fn get_scope_sources<S: AsRef<str>>(data: &AppData, user_id: S) -> anyhow::Result<Vec<ScopeSource>> {
     let mut conn = data.pool.get_conn()?;
 
-    let rows = conn.exec::<Row, &str, Params>("SELECT scope_name FROM scopes WHERE user_id = :user_id", params! {
+    let rows = conn.exec::<Row, &str, Params>("SELECT scope_name, NULL AS role_name FROM scopes WHERE user_id = :user_id \
+        UNION ALL \
+        SELECT role_scopes.scope_name, role_scopes.role_name FROM user_roles \
+        INNER JOIN role_scopes ON role_scopes.role_name = user_roles.role_name \
+        WHERE user_roles.user_id = :user_id", params! {
         "user_id" => user_id.as_ref()
     })?;
 
     let scopes = rows.into_iter()
-        .map(|r| r.get("scope_name").unwrap())
+        .map(|r| ScopeSource {
+            scope:  r.get("scope_name").unwrap(),
+            role:   r.get("role_name").unwrap(),
+        })
         .collect();
 
     Ok(scopes)
-}
\ No newline at end of file
+}
+
+pub fn user_exists<S: AsRef<str>>(data: &AppData, user_id: S) -> anyhow::Result<bool> {
+    let mut conn = data.pool.get_conn()?;
+
+    let row: Option<Row> = conn.exec_first("SELECT 1 FROM users WHERE user_id = :user_id", params! {
+        "user_id" => user_id.as_ref()
+    })?;
+
+    Ok(row.is_some())
+}
@@ -1,8 +1,11 @@
 use std::sync::Arc;
-use actix_web::{get, web, HttpResponse};
-use mysql::{prelude::Queryable, Row, Params, params};
-use crate::env::AppData;
+use actix_web::{get, web};
+use mysql::{prelude::Queryable, Row, Params, Transaction, TxOpts, params};
+use crate::env::{AppData, Env, NewUserPolicy};
 use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::apis::provider::{IdentityClaims, ProviderError, TokenResponse, DEFAULT_PROVIDER};
 use serde::Deserialize;
 use rand::Rng;
 
@@ -13,172 +16,74 @@ pub struct GrantQuery {
     state:  String,
 }
 
-#[derive(Deserialize)]
-struct JwtPayload {
-    sub:        String,
-    name:       Option<String>,
-    email:      String,
-    picture:    Option<String>,
-    nonce:      String,
+struct StateRow {
+    nonce:                  String,
+    redirect_uri_base64:    String,
+    api_name:               Option<String>,
+    provider:               String,
+    code_challenge:         Option<String>,
+    /// The nonce the client provided to /oauth2/login, not to be confused with the nonce we sent to the identity provider
+    client_nonce:           Option<String>,
 }
 
-// 1 day
-const SESSION_EXPIRY_TIME_SECS: u64 = 86_400;
-
 #[get("/oauth2/grant")]
 pub async fn grant(data: web::Data<Arc<AppData>>, query: web::Query<GrantQuery>) -> HttpResult {
-    let mut conn = data.pool.get_conn()?;
+    let GrantQuery { error, code, state } = query.into_inner();
 
     // Check if we got a code or an error
-    match (&query.code, &query.error) {
+    match (code, error) {
         (Some(code), None) => {
             // We got a code, good. Query the database for the data associated with the state we got
-            let state_row: Row = match conn.exec_first("SELECT nonce,redirect_uri FROM states WHERE state = :state", params! {
-                "state" => &query.state
-            })? {
-                Some(ru) => ru,
-                None => return Err(Error::NotFound("Provided parameter 'state' does not exist.")),
-            };
+            let state_lookup = state.clone();
+            let state_row = run_blocking(&data, move |data| get_state(data, &state_lookup)).await?;
+            let redirect_uri_base64 = state_row.redirect_uri_base64.clone();
 
-            let nonce: String = state_row.get("nonce").unwrap();
-            let redirect_uri_base64: String = state_row.get("redirect_uri").unwrap();
-
-            // Exchange the grant token (i.e code) for a refresh- & ID token
-            let exchange_response = crate::apis::google_auth::exchange_grant_token(&data.env, &code, &format!("{}/oauth2/grant", &data.env.host))?;
-
-            // The ID token is a JWT, which have the format xx.yy.zz, where
-            // x: The header
-            // y: The payload
-            // z: The signature
-            // As per Google docs we do not have to verify the signature here, we can assume it is trusted
-            // This means we don't need the header either, leaving us the payload.
-            let jwt_parts = exchange_response.id_token.split(".").collect::<Vec<&str>>();
-            let jwt_payload_base64 = jwt_parts.get(1).unwrap();
-
-            // The payload is encoded as base64, convert this to a UTF-8 JSON string
-            let jwt = base64::decode(jwt_payload_base64)?;
-            let jwt_payload = String::from_utf8(jwt)?;
-
-            // Lastly, deserialize the String
-            let jwt_payload: JwtPayload = serde_json::from_str(&jwt_payload)?;
-
-            // We must verify the nonce we gave in the original GET /login with the nonce contained in the ID token
-            // If it is not equal, drop the state record and return a 401.
-            //TODO Would a different status code be more appropriate here?
-            if jwt_payload.nonce.ne(&nonce) {
-                conn.exec_drop("DELETE FROM states WHERE state = :state", params! {
-                    "state" => &query.state
-                })?;
-
-                return Err(Error::Unauthorized)
-            }
+            let provider = data.provider(&state_row.provider)?;
 
-            let row: Option<Row> = conn.exec_first::<Row, &str, Params>("SELECT refresh_token,name,email,picture FROM users WHERE user_id = :sub", params! {
-                "sub" => &jwt_payload.sub
-            })?;
+            let exchange = async {
+                // Exchange the grant token (i.e code) for a refresh- & ID token
+                let timer = data.metrics.time_provider_call(provider.name(), "exchange_code");
+                let exchange_response = provider.exchange_code(&code, &format!("{}/oauth2/grant", &data.env.host)).await?;
+                timer.observe_duration();
 
-            // Check if the database already has a record of our user
-            // If it does, check for the selected fields if they are up-to-date with the newly provided data
-            // If it does not, insert a new row with the data that we have.
-            match row {
-                Some(r) => {
-                    // We know now that the record already exists
-
-                    // Check the refresh token, and update if necessary
-                    if let Some(refresh_token) = exchange_response.refresh_token {
-                        let existing_refresh_token: String = r.get("refresh_token").unwrap();
-                        if refresh_token.ne(&existing_refresh_token) {
-                            conn.exec_drop("UPDATE users SET refresh_token = :refresh_token WHERE user_id = :sub", params! {
-                                "refresh_token" => &refresh_token,
-                                "sub" => &jwt_payload.sub
-                            })?;
-                        }
-                    }
-
-                    // Check the email, and update if necessary
-                    let existing_email: String = r.get("email").unwrap();
-                    if jwt_payload.email.ne(&existing_email) {
-                        conn.exec_drop("UPDATE users SET email = :email WHERE user_id = :sub", params! {
-                            "email" => &jwt_payload.email,
-                            "sub" => &jwt_payload.sub
-                        })?;
-                    }
-
-                    // Check the name, and update if necessary
-                    if let Some(name) = jwt_payload.name {
-                        let existing_name: String = r.get("name").unwrap();
-                        if name.ne(&existing_name) {
-                            conn.exec_drop("UPDATE users SET name = :name WHERE user_id = :sub", params! {
-                                "name" => &name,
-                                "sub" => &jwt_payload.sub
-                            })?;
-                        }
-                    }
-
-                    // Check the picture, and update if necessary
-                    if let Some(picture) = jwt_payload.picture {
-                        let exiting_picture: String = r.get("picture").unwrap();
-                        if picture.ne(&exiting_picture) {
-                            conn.exec_drop("UPDATE users SET picture = :picture WHERE user_id = :sub", params! {
-                                "picture" => &picture,
-                                "sub" => &jwt_payload.sub
-                            })?;
-                        }
-                    }
-                },
-                None => {
-                    // No record of the user exists yet
-                    conn.exec_drop("INSERT INTO users (user_id, active, name, email, picture, refresh_token) VALUES (:user_id, true, :name, :email, :picture, :refresh_token)", params! {
-                        "user_id" => &jwt_payload.sub,
-                        "name" => &jwt_payload.name,
-                        "email" => &jwt_payload.email,
-                        "picture" => &jwt_payload.picture,
-                        "refresh_token" => &exchange_response.refresh_token
-                    })?
-                }
-            }
+                // The ID token is a JWT signed by the identity provider. Verify its signature against the provider's published keys,
+                // and check that it was issued by the provider, for our client, and has not yet expired.
+                let timer = data.metrics.time_provider_call(provider.name(), "verify_id_token");
+                let claims = provider.verify_id_token(&exchange_response.id_token).await?;
+                timer.observe_duration();
+                Ok::<_, Error>((exchange_response, claims))
+            };
 
-            // We can now be sure a record exists for the user, and that it is as up to date as Google wants it to be
-            // Create a new session for the user
-            let session_id: String = rand::thread_rng().sample_iter(rand::distributions::Alphanumeric).take(32).map(char::from).collect();
-            let expiry = chrono::Utc::now().timestamp() + SESSION_EXPIRY_TIME_SECS as i64;
+            let (exchange_response, claims) = match exchange.await {
+                Ok(r) => r,
+                Err(e) => {
+                    // If the provider rejected the code, record the error code it returned
+                    let (label, reason) = match provider_error(&e) {
+                        Some(pe) => (crate::metrics::provider_error_label(&pe.error).to_string(), format!("the identity provider returned {}", pe.error)),
+                        None => ("exchange_failed".to_string(), "the code could not be exchanged, or the ID token is invalid".to_string()),
+                    };
 
-            // Inser the new session into the database
-            conn.exec_drop("INSERT INTO sessions (session_id, user_id, expiry) VALUES (:session_id, :user_id, :expiry)", params! {
-                "session_id" => &session_id,
-                "user_id" => &jwt_payload.sub,
-                "expiry" => &expiry
-            })?;
+                    run_blocking(&data, move |data| delete_state(data, &state, None, &label, &reason)).await?;
+                    return Err(e);
+                }
+            };
+            let user_id = provider.user_id(&claims.sub);
 
-            // Delete the state record, it is no longer relevant
-            conn.exec_drop("DELETE FROM states WHERE state = :state", params! {
-                "state" => &query.state
-            })?;
+            let authorization_code = run_blocking(&data, move |data| create_session(data, &state, state_row, exchange_response, claims, &user_id)).await?;
 
             // The redirect stored in the database is base64, decode it to a UTF-8 String
             let redirect_uri = base64::decode(&redirect_uri_base64)?;
             let redirect_uri = String::from_utf8(redirect_uri)?;
 
-            // Append the generated session ID to the redirect uri provided in GET /login
-            let redirect_uri = if redirect_uri.contains("?") {
-                format!("{}&session_id={}", &redirect_uri, session_id)
-            } else {
-                format!("{}?session_id={}", &redirect_uri, session_id)
-            };
-
-            let mut ctx = tera::Context::new();
-            ctx.insert("redirect_uri", &redirect_uri);
-
             // Finally, put the redirect uri in the redirect template and return that as body
-            let body = data.tera.render("redirect.html", &ctx)?;
-            return Ok(HttpResponse::Ok().body(&body));
+            super::redirect_page(&data.tera, &with_authorization_code(&redirect_uri, &authorization_code))
         },
         (None, Some(error)) => {
             // We did not get a code, but rather an error
             // We can immediately drop the state, as it's no longer relevant
-            conn.exec_drop("DELETE FROM states WHERE state = :state", params! {
-                "state" => &query.state
-            })?;
+            let reason = format!("the identity provider returned {}", error);
+            let label = crate::metrics::provider_error_label(&error).to_string();
+            run_blocking(&data, move |data| delete_state(data, &state, None, &label, &reason)).await?;
 
             // We want to tailor our response code to the error we got
             match error.as_str() {
@@ -191,4 +96,358 @@ pub async fn grant(data: web::Data<Arc<AppData>>, query: web::Query<GrantQuery>)
         },
         _ => Err(Error::BadRequest("Expected either an 'error' or a 'code', neither were provided."))
     }
-}
\ No newline at end of file
+}
+
+/// Append the authorization code to the redirect uri provided in GET /login.
+/// The client exchanges it for the session in POST /oauth2/token, so the session ID never ends up in the browser
+fn with_authorization_code(redirect_uri: &str, authorization_code: &str) -> String {
+    if redirect_uri.contains('?') {
+        format!("{}&code={}", redirect_uri, authorization_code)
+    } else {
+        format!("{}?code={}", redirect_uri, authorization_code)
+    }
+}
+
+/// The error response of the identity provider, if that is what caused the error
+fn provider_error(e: &Error) -> Option<&ProviderError> {
+    match e {
+        Error::Anyhow(e) => e.downcast_ref::<ProviderError>(),
+        _ => None,
+    }
+}
+
+fn get_state(data: &AppData, state: &str) -> Result<StateRow, Error> {
+    let mut conn = data.pool.get_conn()?;
+
+    let state_row: Row = match conn.exec_first("SELECT nonce,client_nonce,redirect_uri,api_name,provider,code_challenge,created_at FROM states WHERE state = :state", params! {
+        "state" => state
+    })? {
+        Some(ru) => ru,
+        None => return Err(Error::NotFound("Provided parameter 'state' does not exist.")),
+    };
+
+    // The user took too long to log in, they'll have to start over
+    let created_at: i64 = state_row.get("created_at").unwrap();
+    if created_at + data.env.state_ttl_secs <= chrono::Utc::now().timestamp() {
+        delete_state(data, state, None, "state_expired", "the login expired")?;
+        return Err(Error::UnauthorizedMsg("The login has expired, please try again"));
+    }
+
+    Ok(StateRow {
+        nonce:                  state_row.get("nonce").unwrap(),
+        redirect_uri_base64:    state_row.get("redirect_uri").unwrap(),
+        api_name:               state_row.get("api_name").unwrap(),
+        provider:               state_row.get::<Option<String>, &str>("provider").unwrap().unwrap_or_else(|| DEFAULT_PROVIDER.to_string()),
+        code_challenge:         state_row.get("code_challenge").unwrap(),
+        client_nonce:           state_row.get("client_nonce").unwrap(),
+    })
+}
+
+/// Delete the state of a login which failed, recording why it failed.
+/// `error` is the label under which the failure is counted in the metrics
+fn delete_state(data: &AppData, state: &str, user_id: Option<&str>, error: &str, reason: &str) -> Result<(), Error> {
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    delete_state_in(data, &mut tx, state, user_id, error, reason)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Like `delete_state`, as part of the caller's transaction
+fn delete_state_in<Q: Queryable>(data: &AppData, conn: &mut Q, state: &str, user_id: Option<&str>, error: &str, reason: &str) -> Result<(), Error> {
+    data.metrics.grant_error(error);
+
+    let api_name: Option<String> = conn.exec_first::<Option<String>, &str, Params>("SELECT api_name FROM states WHERE state = :state", params! {
+        "state" => state
+    })?.flatten();
+
+    conn.exec_drop("DELETE FROM states WHERE state = :state", params! {
+        "state" => state
+    })?;
+
+    audit::record(conn, AuditEvent::GrantFailed, user_id, api_name.as_deref(), Some(reason))?;
+    Ok(())
+}
+
+/// Create or update the user from the verified ID token, and create a new session for them.
+/// Returns the one-time authorization code with which the client can obtain the session
+fn create_session(data: &AppData, state: &str, state_row: StateRow, exchange_response: TokenResponse, claims: IdentityClaims, user_id: &str) -> Result<String, Error> {
+    let mut conn = data.pool.get_conn()?;
+
+    // We must verify the nonce we gave in the original GET /login with the nonce contained in the ID token
+    // If it is not equal, drop the state record and return a 401.
+    //TODO Would a different status code be more appropriate here?
+    if claims.nonce.as_deref() != Some(&state_row.nonce) {
+        delete_state(data, state, Some(user_id), "nonce_mismatch", "the nonce did not match")?;
+        return Err(Error::Unauthorized)
+    }
+
+    // States created before PKCE was required can not be completed, as there is nothing to check the verifier against
+    let (api_name, code_challenge) = match (state_row.api_name, state_row.code_challenge) {
+        (Some(api_name), Some(code_challenge)) => (api_name, code_challenge),
+        _ => {
+            delete_state(data, state, Some(user_id), "missing_pkce", "the login was started without PKCE")?;
+            return Err(Error::BadRequest("The login was started without an API name or PKCE code challenge"));
+        }
+    };
+
+    if !is_allowed_domain(&data.env, &claims) {
+        delete_state(data, state, Some(user_id), "domain_not_allowed", "the user's domain is not allowed")?;
+        return Err(Error::Forbidden("Users of this domain are not allowed to sign in"));
+    }
+
+    // Everything from here on is committed along with its audit events, or not at all
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    let select_user = |tx: &mut Transaction<'_>| tx.exec_first::<Row, &str, Params>("SELECT active,refresh_token,name,email,picture FROM users WHERE user_id = :user_id", params! {
+        "user_id" => user_id
+    });
+
+    let mut row: Option<Row> = select_user(&mut tx)?;
+
+    // Someone who was invited by their email address takes over the pre-provisioned user when they first log in
+    if row.is_none() && claims.email_verified {
+        if let Some(email) = &claims.email {
+            if bind_invitation(&mut tx, email, user_id, &api_name, &state_row.provider)? {
+                row = select_user(&mut tx)?;
+            }
+        }
+    }
+
+    // Check if the database already has a record of our user
+    // If it does, check for the selected fields if they are up-to-date with the newly provided data
+    // If it does not, insert a new row with the data that we have.
+    match row {
+        Some(r) => {
+            // We know now that the record already exists
+
+            // Users pending approval or deactivated users may not sign in, regardless of the new user policy
+            let active: bool = r.get("active").unwrap();
+            if !active {
+                delete_state_in(data, &mut tx, state, Some(user_id), "user_inactive", "the user is not active")?;
+                tx.commit()?;
+                return Err(Error::Forbidden("The account has to be approved before it can be used"));
+            }
+
+            // Check the refresh token, and update if necessary
+            if let Some(refresh_token) = exchange_response.refresh_token {
+                let existing_refresh_token: Option<String> = r.get("refresh_token").unwrap();
+                if existing_refresh_token.as_ref() != Some(&refresh_token) {
+                    tx.exec_drop("UPDATE users SET refresh_token = :refresh_token WHERE user_id = :user_id", params! {
+                        "refresh_token" => &refresh_token,
+                        "user_id" => user_id
+                    })?;
+                }
+            }
+
+            // Check the email, and update if necessary
+            if let Some(email) = claims.email {
+                let existing_email: Option<String> = r.get("email").unwrap();
+                if existing_email.as_ref() != Some(&email) {
+                    tx.exec_drop("UPDATE users SET email = :email WHERE user_id = :user_id", params! {
+                        "email" => &email,
+                        "user_id" => user_id
+                    })?;
+                }
+            }
+
+            // Check the name, and update if necessary
+            if let Some(name) = claims.name {
+                let existing_name: Option<String> = r.get("name").unwrap();
+                if existing_name.as_ref() != Some(&name) {
+                    tx.exec_drop("UPDATE users SET name = :name WHERE user_id = :user_id", params! {
+                        "name" => &name,
+                        "user_id" => user_id
+                    })?;
+                }
+            }
+
+            // Check the picture, and update if necessary
+            if let Some(picture) = claims.picture {
+                let exiting_picture: Option<String> = r.get("picture").unwrap();
+                if exiting_picture.as_ref() != Some(&picture) {
+                    tx.exec_drop("UPDATE users SET picture = :picture WHERE user_id = :user_id", params! {
+                        "picture" => &picture,
+                        "user_id" => user_id
+                    })?;
+                }
+            }
+        },
+        None => {
+            // No record of the user exists yet, whether they may be created depends on the configured policy
+            let active = match data.env.new_user_policy {
+                NewUserPolicy::Activate => true,
+                NewUserPolicy::Pending => false,
+                NewUserPolicy::Reject => {
+                    delete_state_in(data, &mut tx, state, Some(user_id), "new_user_rejected", "new users are rejected")?;
+                    tx.commit()?;
+                    return Err(Error::Forbidden("New users are not allowed to sign in"));
+                }
+            };
+
+            tx.exec_drop("INSERT INTO users (user_id, active, name, email, picture, refresh_token, provider) VALUES (:user_id, :active, :name, :email, :picture, :refresh_token, :provider)", params! {
+                "user_id" => user_id,
+                "active" => active,
+                "name" => &claims.name,
+                "email" => &claims.email,
+                "picture" => &claims.picture,
+                "refresh_token" => &exchange_response.refresh_token,
+                "provider" => &state_row.provider
+            })?;
+
+            audit::record(&mut tx, AuditEvent::UserCreated, Some(user_id), Some(&api_name), if active { None } else { Some("pending approval") })?;
+
+            if !active {
+                delete_state_in(data, &mut tx, state, Some(user_id), "pending_approval", "the user is pending approval")?;
+                tx.commit()?;
+                return Err(Error::Forbidden("The account was created, but has to be approved before it can be used"));
+            }
+        }
+    }
+
+    // The identity provider handed us an access token as well, cache it so the first call to token::get doesn't have to refresh
+    let access_token_expiry = chrono::Utc::now().timestamp() + exchange_response.expires_in;
+    crate::endpoints::token::cache_access_token(&mut tx, user_id, &exchange_response.access_token, access_token_expiry)?;
+
+    // We can now be sure a record exists for the user, and that it is as up to date as the identity provider wants it to be
+    // Create a new session for the user
+    let session_id: String = rand::thread_rng().sample_iter(rand::distributions::Alphanumeric).take(32).map(char::from).collect();
+    let now = chrono::Utc::now().timestamp();
+    let absolute_expiry = now + data.env.session_absolute_timeout_secs;
+    let expiry = std::cmp::min(now + data.env.session_idle_timeout_secs, absolute_expiry);
+
+    // Inser the new session into the database
+    tx.exec_drop("INSERT INTO sessions (session_id, user_id, expiry, absolute_expiry, api_name) VALUES (:session_id, :user_id, :expiry, :absolute_expiry, :api_name)", params! {
+        "session_id" => &session_id,
+        "user_id" => user_id,
+        "expiry" => &expiry,
+        "absolute_expiry" => &absolute_expiry,
+        "api_name" => &api_name
+    })?;
+
+    audit::record(&mut tx, AuditEvent::GrantSucceeded, Some(user_id), Some(&api_name), Some(&format!("with {}", state_row.provider)))?;
+    audit::record(&mut tx, AuditEvent::SessionCreated, Some(user_id), Some(&api_name), None)?;
+
+    // Delete the state record, it is no longer relevant
+    tx.exec_drop("DELETE FROM states WHERE state = :state", params! {
+        "state" => state
+    })?;
+
+    let authorization_code: String = rand::thread_rng().sample_iter(rand::distributions::Alphanumeric).take(64).map(char::from).collect();
+    tx.exec_drop("INSERT INTO authorization_codes (code, session_id, api_name, code_challenge, nonce, expiry) VALUES (:code, :session_id, :api_name, :code_challenge, :nonce, :expiry)", params! {
+        "code" => &authorization_code,
+        "session_id" => &session_id,
+        "api_name" => &api_name,
+        "code_challenge" => &code_challenge,
+        "nonce" => &state_row.client_nonce,
+        "expiry" => now + data.env.authorization_code_lifetime_secs
+    })?;
+
+    tx.commit()?;
+    Ok(authorization_code)
+}
+
+/// Check if the user belongs to one of the allowed hosted domains, or has a verified email address in one of the allowed email domains.
+/// If no domains are configured, every user is allowed
+fn is_allowed_domain(env: &Env, claims: &IdentityClaims) -> bool {
+    if env.allowed_hosted_domains.is_empty() && env.allowed_email_domains.is_empty() {
+        return true;
+    }
+
+    let hosted_domain_allowed = match &claims.hosted_domain {
+        Some(hd) => env.allowed_hosted_domains.iter().any(|d| d.eq_ignore_ascii_case(hd)),
+        None => false,
+    };
+
+    let email_domain_allowed = match claims.email.as_deref().and_then(|e| e.rsplit_once('@')) {
+        Some((_, domain)) if claims.email_verified => env.allowed_email_domains.iter().any(|d| d.eq_ignore_ascii_case(domain)),
+        _ => false,
+    };
+
+    hosted_domain_allowed || email_domain_allowed
+}
+
+/// Bind the invited user with this email address, if there is one, to the user ID of the user logging in.
+/// Returns whether an invitation was found
+fn bind_invitation(tx: &mut Transaction<'_>, email: &str, user_id: &str, api_name: &str, provider: &str) -> Result<bool, Error> {
+    // The invitation stays locked until the caller's transaction ends, so two concurrent logins with the same email address can't both bind it
+    let invited_user_id: String = match tx.exec_first("SELECT user_id FROM users WHERE invited = true AND email = :email FOR UPDATE", params! {
+        "email" => email.to_lowercase()
+    })? {
+        Some(id) => id,
+        None => return Ok(false),
+    };
+
+    tx.exec_drop("UPDATE users SET user_id = :user_id, invited = false, provider = :provider WHERE user_id = :invited_user_id", params! {
+        "user_id" => user_id,
+        "provider" => provider,
+        "invited_user_id" => &invited_user_id
+    })?;
+
+    for query in [
+        "UPDATE scopes SET user_id = :user_id WHERE user_id = :invited_user_id",
+        "UPDATE user_roles SET user_id = :user_id WHERE user_id = :invited_user_id",
+    ] {
+        tx.exec_drop(query, params! {
+            "user_id" => user_id,
+            "invited_user_id" => &invited_user_id
+        })?;
+    }
+
+    audit::record(tx, AuditEvent::InvitationAccepted, Some(user_id), Some(api_name), Some(&format!("bound {}", invited_user_id)))?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apis::provider::IdentityClaims;
+    use crate::tests::env;
+    use super::{is_allowed_domain, with_authorization_code};
+
+    fn claims(email: &str, email_verified: bool, hosted_domain: Option<&str>) -> IdentityClaims {
+        IdentityClaims {
+            sub:            "1234".to_string(),
+            name:           None,
+            email:          Some(email.to_string()),
+            picture:        None,
+            nonce:          None,
+            email_verified,
+            hosted_domain:  hosted_domain.map(String::from),
+        }
+    }
+
+    #[test]
+    fn allows_everyone_without_configured_domains() {
+        assert!(is_allowed_domain(&env(&[]), &claims("user@anywhere.com", false, None)));
+    }
+
+    #[test]
+    fn allows_matching_hosted_domain() {
+        let env = env(&[("ALLOWED_HOSTED_DOMAINS", "example.com,example.org")]);
+        assert!(is_allowed_domain(&env, &claims("user@gmail.com", true, Some("example.org"))));
+        assert!(!is_allowed_domain(&env, &claims("user@example.com", true, Some("evil.com"))));
+        assert!(!is_allowed_domain(&env, &claims("user@example.com", true, None)));
+    }
+
+    #[test]
+    fn allows_matching_email_domain_only_if_verified() {
+        let env = env(&[("ALLOWED_EMAIL_DOMAINS", "example.com")]);
+        assert!(is_allowed_domain(&env, &claims("user@example.com", true, None)));
+        assert!(!is_allowed_domain(&env, &claims("user@example.com", false, None)));
+        assert!(!is_allowed_domain(&env, &claims("user@example.com.evil.com", true, None)));
+        assert!(!is_allowed_domain(&env, &claims("user@sub.example.com", true, None)));
+    }
+
+    #[test]
+    fn compares_domains_case_insensitively() {
+        let env = env(&[("ALLOWED_HOSTED_DOMAINS", "Example.com"), ("ALLOWED_EMAIL_DOMAINS", "example.ORG")]);
+        assert!(is_allowed_domain(&env, &claims("user@gmail.com", true, Some("EXAMPLE.COM"))));
+        assert!(is_allowed_domain(&env, &claims("user@Example.Org", true, None)));
+    }
+
+    #[test]
+    fn appends_authorization_code() {
+        assert_eq!(with_authorization_code("https://app.example.com/callback", "abc"), "https://app.example.com/callback?code=abc");
+        assert_eq!(with_authorization_code("https://app.example.com/callback?tenant=1", "abc"), "https://app.example.com/callback?tenant=1&code=abc");
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params, params};
+use serde::{Deserialize, Serialize};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::{authorization, check_token};
+
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
+    /// The session ID to introspect
+    token:  String,
+}
+
+/// The introspection response as per RFC 7662. Only `active` is included for inactive tokens
+#[derive(Serialize, Default)]
+struct IntrospectResponse {
+    active:     bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub:        Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp:        Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope:      Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id:  Option<String>,
+}
+
+#[post("/oauth2/introspect")]
+pub async fn introspect(data: web::Data<Arc<AppData>>, req: HttpRequest, form: web::Form<IntrospectRequest>) -> HttpResult {
+    let api_token = authorization!(req);
+    check_token!(req, data);
+    let token = form.into_inner().token;
+
+    let response = run_blocking(&data, move |data| introspect_session(data, &api_token, &token)).await?;
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn introspect_session(data: &AppData, api_token: &str, session_id: &str) -> Result<IntrospectResponse, Error> {
+    // Introspecting counts as using the session, so this extends its idle expiry as well.
+    // Sessions issued for another API are reported as inactive, so they can't be probed
+    match crate::endpoints::session::check_session(data, Some(api_token), session_id) {
+        Ok(_) => {},
+        Err(Error::NotFound(_)) | Err(Error::Unauthorized) | Err(Error::UnauthorizedMsg(_)) => return Ok(IntrospectResponse::default()),
+        Err(e) => return Err(e),
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let row: Row = match conn.exec_first::<Row, &str, Params>("SELECT sessions.user_id,sessions.expiry,sessions.api_name,users.active FROM sessions JOIN users ON sessions.user_id = users.user_id WHERE sessions.session_id = :session_id", params! {
+        "session_id" => session_id
+    })? {
+        Some(r) => r,
+        None => return Ok(IntrospectResponse::default()),
+    };
+
+    let active: bool = row.get("active").unwrap();
+    if !active {
+        return Ok(IntrospectResponse::default());
+    }
+
+    let user_id: String = row.get("user_id").unwrap();
+    let scopes = crate::endpoints::get_scopes(data, &user_id)?;
+
+    Ok(IntrospectResponse {
+        active:     true,
+        sub:        Some(user_id),
+        exp:        row.get("expiry").unwrap(),
+        scope:      Some(scopes.join(" ")),
+        client_id:  row.get("api_name").unwrap(),
+    })
+}
@@ -1,2 +1,95 @@
 pub mod login;
 pub mod grant;
+pub mod token;
+pub mod introspect;
+
+use actix_web::HttpResponse;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use crate::error::Error;
+
+/// The only PKCE code challenge method we support. The `plain` method offers no protection if the challenge leaks
+const CODE_CHALLENGE_METHOD: &str = "S256";
+
+/// Compute the S256 code challenge belonging to a PKCE code verifier, as per RFC 7636
+fn code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(Sha256::digest(code_verifier.as_bytes()), base64::URL_SAFE_NO_PAD)
+}
+
+/// A code challenge or verifier may only contain unreserved URI characters, as per RFC 7636
+fn is_pkce_charset(value: &str) -> bool {
+    value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+}
+
+/// Render the page which sends the browser on to the URI
+fn redirect_page(tera: &tera::Tera, uri: &str) -> Result<HttpResponse, Error> {
+    let mut ctx = tera::Context::new();
+    ctx.insert("redirect_uri", uri);
+
+    let body = tera.render("redirect.html", &ctx)?;
+    Ok(HttpResponse::Ok().body(body))
+}
+
+/// Check the code verifier provided when exchanging a code against the code challenge provided at login
+fn verify_code_verifier(code_verifier: &str, code_challenge: &str) -> bool {
+    if !(43..=128).contains(&code_verifier.len()) || !is_pkce_charset(code_verifier) {
+        return false;
+    }
+
+    self::code_challenge(code_verifier).as_bytes().ct_eq(code_challenge.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::MessageBody;
+    use crate::env::AppData;
+
+    #[test]
+    fn redirect_page_sends_browser_to_uri() {
+        let data = AppData::without_providers(&crate::tests::env(&[])).unwrap();
+
+        // A provider's authorization URL is not checked like a return URI, so the page must encode whatever it gets
+        for uri in ["https://app.example.com/callback?code=abc", "https://idp.example.com/authorize?x=';alert(1)//</script>"] {
+            let body = super::redirect_page(&data.tera, uri).unwrap().into_body().try_into_bytes().unwrap();
+            assert_eq!(crate::tests::redirect_target(&body), uri);
+        }
+    }
+
+    // The example of RFC 7636 Appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn computes_rfc_7636_challenge() {
+        assert_eq!(super::code_challenge(VERIFIER), CHALLENGE);
+        assert!(super::verify_code_verifier(VERIFIER, CHALLENGE));
+    }
+
+    #[test]
+    fn rejects_wrong_verifier() {
+        let mut verifier = VERIFIER.to_string();
+        verifier.replace_range(..1, "e");
+        assert!(!super::verify_code_verifier(&verifier, CHALLENGE));
+    }
+
+    #[test]
+    fn rejects_verifier_of_invalid_length() {
+        for verifier in ["a".repeat(42), "a".repeat(129)] {
+            assert!(!super::verify_code_verifier(&verifier, &super::code_challenge(&verifier)));
+        }
+
+        for verifier in ["a".repeat(43), "a".repeat(128)] {
+            assert!(super::verify_code_verifier(&verifier, &super::code_challenge(&verifier)));
+        }
+    }
+
+    #[test]
+    fn rejects_verifier_outside_charset() {
+        for c in ['+', '/', '=', ' ', '%', 'é'] {
+            let verifier = format!("{}{}", &VERIFIER[1..], c);
+            assert!(!super::verify_code_verifier(&verifier, &super::code_challenge(&verifier)));
+        }
+
+        assert!(super::is_pkce_charset("azAZ09-._~"));
+    }
+}
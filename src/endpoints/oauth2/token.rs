@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params, params, TxOpts};
+use serde::{Deserialize, Serialize};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::endpoints::session::IssuedTokens;
+use crate::authorization;
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    grant_type:     String,
+    code:           String,
+    code_verifier:  String,
+}
+
+#[derive(Serialize)]
+struct TokenResponse {
+    session_id: String,
+    expiry:     i64,
+    /// Only included if Authlander is configured to issue tokens
+    #[serde(flatten)]
+    tokens:     Option<IssuedTokens>,
+}
+
+/// Exchange the authorization code handed out by /oauth2/grant for the session.
+/// The request must be made with the API token of the API the login was started for,
+/// and carry the PKCE verifier belonging to the code challenge provided to /oauth2/login.
+#[post("/oauth2/token")]
+pub async fn token(data: web::Data<Arc<AppData>>, req: HttpRequest, form: web::Form<TokenRequest>) -> HttpResult {
+    let api_token = authorization!(req);
+    let form = form.into_inner();
+
+    if form.grant_type != "authorization_code" {
+        return Err(Error::BadRequest("Only the authorization_code grant_type is supported"));
+    }
+
+    let response = run_blocking(&data, move |data| exchange_code(data, &api_token, &form.code, &form.code_verifier)).await?;
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn exchange_code(data: &AppData, api_token: &str, code: &str, code_verifier: &str) -> Result<TokenResponse, Error> {
+    let api_name = match crate::endpoints::get_api_name(data, api_token)? {
+        Some(n) => n,
+        None => return Err(Error::Unauthorized),
+    };
+
+    let mut conn = data.pool.get_conn()?;
+
+    // The code may only be used once, so it is deleted before anything else is checked
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    let code_row: Row = match tx.exec_first::<Row, &str, Params>("SELECT session_id,api_name,code_challenge,nonce,expiry FROM authorization_codes WHERE code = :code FOR UPDATE", params! {
+        "code" => code
+    })? {
+        Some(r) => r,
+        None => return Err(Error::BadRequest("The provided code is invalid or has already been used")),
+    };
+
+    tx.exec_drop("DELETE FROM authorization_codes WHERE code = :code", params! {
+        "code" => code
+    })?;
+    tx.commit()?;
+
+    let session_id: String = code_row.get("session_id").unwrap();
+    let code_api_name: String = code_row.get("api_name").unwrap();
+    let code_challenge: String = code_row.get("code_challenge").unwrap();
+    let code_expiry: i64 = code_row.get("expiry").unwrap();
+    let nonce: Option<String> = code_row.get("nonce").unwrap();
+
+    let failure = if code_expiry <= chrono::Utc::now().timestamp() {
+        Some(Error::BadRequest("The provided code has expired"))
+    } else if code_api_name != api_name {
+        Some(Error::UnauthorizedMsg("The code was not issued for this API"))
+    } else if !super::verify_code_verifier(code_verifier, &code_challenge) {
+        Some(Error::BadRequest("The provided code_verifier does not match the code_challenge"))
+    } else {
+        None
+    };
+
+    let session_row: Row = match conn.exec_first::<Row, &str, Params>("SELECT user_id,expiry FROM sessions WHERE session_id = :session_id", params! {
+        "session_id" => &session_id
+    })? {
+        Some(r) => r,
+        None => return Err(Error::NotFound("Session does not exist")),
+    };
+    let user_id: String = session_row.get("user_id").unwrap();
+    let expiry: i64 = session_row.get("expiry").unwrap();
+
+    // Nobody can obtain the session anymore, so there is no point in keeping it around
+    if let Some(e) = failure {
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        tx.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
+            "session_id" => &session_id
+        })?;
+
+        audit::record(&mut tx, AuditEvent::GrantFailed, Some(&user_id), Some(&api_name), Some(&format!("code exchange rejected: {}", e)))?;
+        tx.commit()?;
+        return Err(e);
+    }
+
+    audit::record(&mut conn, AuditEvent::TokenIssued, Some(&user_id), Some(&api_name), Some("exchanged authorization code for session"))?;
+
+    let tokens = match data.signer {
+        Some(_) => Some(crate::endpoints::session::issue_tokens(data, &session_id, nonce.as_deref())?),
+        None => None,
+    };
+
+    Ok(TokenResponse {
+        session_id,
+        expiry,
+        tokens,
+    })
+}
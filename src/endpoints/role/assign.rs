@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, params, TxOpts};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct AssignRequest {
+    role:   String,
+}
+
+#[post("/role/assign/{user_id}")]
+pub async fn assign(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, payload: web::Json<AssignRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+
+    let user_id = user_id.into_inner();
+    run_blocking(&data, move |data| assign_role(data, &caller, &user_id, &payload.role)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn assign_role(data: &AppData, caller: &str, user_id: &str, role_name: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    if !super::role_exists(data, role_name)? {
+        return Err(Error::NotFound("The requested role does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    let existing: Option<Row> = tx.exec_first("SELECT 1 FROM user_roles WHERE user_id = :user_id AND role_name = :role_name", params! {
+        "user_id" => user_id,
+        "role_name" => role_name
+    })?;
+
+    if existing.is_none() {
+        tx.exec_drop("INSERT INTO user_roles (role_name, user_id) VALUES (:role_name, :user_id)", params! {
+            "role_name" => role_name,
+            "user_id" => user_id
+        })?;
+
+        audit::record(&mut tx, AuditEvent::UserRolesChanged, Some(user_id), Some(caller), Some(&format!("assigned {}", role_name)))?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
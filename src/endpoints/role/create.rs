@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct CreateRequest {
+    name:   String,
+    scopes: Vec<String>,
+}
+
+#[post("/role/create")]
+pub async fn create(data: web::Data<Arc<AppData>>, req: HttpRequest, payload: web::Json<CreateRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+    super::validate_role_name(&payload.name)?;
+    for scope in &payload.scopes {
+        crate::endpoints::scope::validate_scope_name(scope)?;
+    }
+
+    let CreateRequest { name, mut scopes } = payload.into_inner();
+    scopes.sort();
+    scopes.dedup();
+
+    run_blocking(&data, move |data| create_role(data, &caller, &name, &scopes)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn create_role(data: &AppData, caller: &str, name: &str, scopes: &[String]) -> Result<(), Error> {
+    if super::role_exists(data, name)? {
+        return Err(Error::Conflict("A role with this name already exists"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("INSERT INTO roles (role_name) VALUES (:role_name)", params! {
+        "role_name" => name
+    })?;
+
+    tx.exec_batch("INSERT INTO role_scopes (role_name, scope_name) VALUES (:role_name, :scope_name)", scopes.iter().map(|scope| params! {
+        "role_name" => name,
+        "scope_name" => scope
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::RoleDefinitionChanged, None, Some(caller), Some(&format!("created {} with [{}]", name, scopes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(())
+}
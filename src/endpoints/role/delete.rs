@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+/// Delete a role. Users who were assigned the role lose the scopes it granted them
+#[post("/role/delete/{role_name}")]
+pub async fn delete(data: web::Data<Arc<AppData>>, req: HttpRequest, role_name: web::Path<String>) -> HttpResult {
+    let caller = check_token!(req, data);
+    let role_name = role_name.into_inner();
+
+    run_blocking(&data, move |data| delete_role(data, &caller, &role_name)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn delete_role(data: &AppData, caller: &str, role_name: &str) -> Result<(), Error> {
+    if !super::role_exists(data, role_name)? {
+        return Err(Error::NotFound("The requested role does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for query in [
+        "DELETE FROM user_roles WHERE role_name = :role_name",
+        "DELETE FROM role_scopes WHERE role_name = :role_name",
+        "DELETE FROM roles WHERE role_name = :role_name",
+    ] {
+        tx.exec_drop(query, params! {
+            "role_name" => role_name
+        })?;
+    }
+
+    audit::record(&mut tx, AuditEvent::RoleDefinitionChanged, None, Some(caller), Some(&format!("deleted {}", role_name)))?;
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::collections::BTreeMap;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::check_token;
+
+#[derive(Serialize)]
+struct Response {
+    roles:  Vec<Role>,
+}
+
+#[derive(Serialize)]
+struct Role {
+    name:   String,
+    scopes: Vec<String>,
+}
+
+#[get("/role/list")]
+pub async fn list(data: web::Data<Arc<AppData>>, req: HttpRequest) -> HttpResult {
+    check_token!(req, data);
+    let roles = run_blocking(&data, get_roles).await?;
+    Ok(HttpResponse::Ok().json(&Response { roles }))
+}
+
+fn get_roles(data: &AppData) -> Result<Vec<Role>, Error> {
+    let mut conn = data.pool.get_conn()?;
+
+    let role_names: Vec<String> = conn.exec("SELECT role_name FROM roles", Params::Empty)?;
+    let mut roles: BTreeMap<String, Vec<String>> = role_names.into_iter()
+        .map(|r| (r, Vec::new()))
+        .collect();
+
+    let rows: Vec<Row> = conn.exec("SELECT role_name,scope_name FROM role_scopes", Params::Empty)?;
+    for row in rows {
+        let role_name: String = row.get("role_name").unwrap();
+        if let Some(scopes) = roles.get_mut(&role_name) {
+            scopes.push(row.get("scope_name").unwrap());
+        }
+    }
+
+    let roles = roles.into_iter()
+        .map(|(name, scopes)| Role { name, scopes })
+        .collect();
+
+    Ok(roles)
+}
@@ -0,0 +1,28 @@
+use crate::env::AppData;
+use crate::error::Error;
+use mysql::{prelude::Queryable, Row, params};
+
+pub mod create;
+pub mod delete;
+pub mod list;
+pub mod set;
+pub mod assign;
+pub mod unassign;
+
+/// The role_name column in the database is a VARCHAR(32)
+fn validate_role_name(role: &str) -> Result<(), Error> {
+    if role.is_empty() || role.len() > 32 {
+        return Err(Error::BadRequest("A role name must be between 1 and 32 characters long"));
+    }
+
+    Ok(())
+}
+
+fn role_exists(data: &AppData, role_name: &str) -> anyhow::Result<bool> {
+    let mut conn = data.pool.get_conn()?;
+    let row: Option<Row> = conn.exec_first("SELECT 1 FROM roles WHERE role_name = :role_name", params! {
+        "role_name" => role_name
+    })?;
+
+    Ok(row.is_some())
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct SetRequest {
+    scopes: Vec<String>,
+}
+
+/// Replace the full set of scopes bundled in a role
+#[post("/role/set/{role_name}")]
+pub async fn set(data: web::Data<Arc<AppData>>, req: HttpRequest, role_name: web::Path<String>, payload: web::Json<SetRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+    for scope in &payload.scopes {
+        crate::endpoints::scope::validate_scope_name(scope)?;
+    }
+
+    let mut scopes = payload.into_inner().scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let role_name = role_name.into_inner();
+    run_blocking(&data, move |data| set_role_scopes(data, &caller, &role_name, &scopes)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn set_role_scopes(data: &AppData, caller: &str, role_name: &str, scopes: &[String]) -> Result<(), Error> {
+    if !super::role_exists(data, role_name)? {
+        return Err(Error::NotFound("The requested role does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("DELETE FROM role_scopes WHERE role_name = :role_name", params! {
+        "role_name" => role_name
+    })?;
+
+    tx.exec_batch("INSERT INTO role_scopes (role_name, scope_name) VALUES (:role_name, :scope_name)", scopes.iter().map(|scope| params! {
+        "role_name" => role_name,
+        "scope_name" => scope
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::RoleDefinitionChanged, None, Some(caller), Some(&format!("set {} to [{}]", role_name, scopes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(())
+}
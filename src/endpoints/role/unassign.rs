@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct UnassignRequest {
+    role:   String,
+}
+
+#[post("/role/unassign/{user_id}")]
+pub async fn unassign(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, payload: web::Json<UnassignRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+
+    let user_id = user_id.into_inner();
+    run_blocking(&data, move |data| unassign_role(data, &caller, &user_id, &payload.role)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn unassign_role(data: &AppData, caller: &str, user_id: &str, role_name: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("DELETE FROM user_roles WHERE user_id = :user_id AND role_name = :role_name", params! {
+        "user_id" => user_id,
+        "role_name" => role_name
+    })?;
+
+    if tx.affected_rows() == 0 {
+        return Err(Error::NotFound("The user is not assigned the requested role"));
+    }
+
+    audit::record(&mut tx, AuditEvent::UserRolesChanged, Some(user_id), Some(caller), Some(&format!("unassigned {}", role_name)))?;
+    tx.commit()?;
+    Ok(())
+}
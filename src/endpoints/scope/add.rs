@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, params, TxOpts};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct AddRequest {
+    scope:  String,
+}
+
+#[post("/scope/add/{user_id}")]
+pub async fn add(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, payload: web::Json<AddRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+    super::validate_scope_name(&payload.scope)?;
+
+    let user_id = user_id.into_inner();
+    run_blocking(&data, move |data| add_scope(data, &caller, &user_id, &payload.scope)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn add_scope(data: &AppData, caller: &str, user_id: &str, scope: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    let existing: Option<Row> = tx.exec_first("SELECT 1 FROM scopes WHERE user_id = :user_id AND scope_name = :scope_name", params! {
+        "user_id" => user_id,
+        "scope_name" => scope
+    })?;
+
+    // Adding a scope the user already has is not an error, but we don't want duplicate rows
+    if existing.is_none() {
+        tx.exec_drop("INSERT INTO scopes (scope_name, user_id) VALUES (:scope_name, :user_id)", params! {
+            "scope_name" => scope,
+            "user_id" => user_id
+        })?;
+
+        audit::record(&mut tx, AuditEvent::ScopesChanged, Some(user_id), Some(caller), Some(&format!("added {}", scope)))?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
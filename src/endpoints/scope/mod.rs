@@ -0,0 +1,15 @@
+use crate::error::Error;
+
+pub mod add;
+pub mod remove;
+pub mod set;
+pub mod users;
+
+/// The scope_name column in the database is a VARCHAR(32)
+pub fn validate_scope_name(scope: &str) -> Result<(), Error> {
+    if scope.is_empty() || scope.len() > 32 {
+        return Err(Error::BadRequest("A scope must be between 1 and 32 characters long"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct RemoveRequest {
+    scope:  String,
+}
+
+#[post("/scope/remove/{user_id}")]
+pub async fn remove(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, payload: web::Json<RemoveRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+
+    let user_id = user_id.into_inner();
+    run_blocking(&data, move |data| remove_scope(data, &caller, &user_id, &payload.scope)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn remove_scope(data: &AppData, caller: &str, user_id: &str, scope: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("DELETE FROM scopes WHERE user_id = :user_id AND scope_name = :scope_name", params! {
+        "user_id" => user_id,
+        "scope_name" => scope
+    })?;
+
+    if tx.affected_rows() == 0 {
+        return Err(Error::NotFound("The user does not have the requested scope"));
+    }
+
+    audit::record(&mut tx, AuditEvent::ScopesChanged, Some(user_id), Some(caller), Some(&format!("removed {}", scope)))?;
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, TxOpts, params};
+use serde::Deserialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct SetRequest {
+    scopes: Vec<String>,
+}
+
+/// Replace the full set of scopes of a user
+#[post("/scope/set/{user_id}")]
+pub async fn set(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, payload: web::Json<SetRequest>) -> HttpResult {
+    let caller = check_token!(req, data);
+    for scope in &payload.scopes {
+        super::validate_scope_name(scope)?;
+    }
+
+    let mut scopes = payload.into_inner().scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let user_id = user_id.into_inner();
+    run_blocking(&data, move |data| set_scopes(data, &caller, &user_id, &scopes)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn set_scopes(data: &AppData, caller: &str, user_id: &str, scopes: &[String]) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("DELETE FROM scopes WHERE user_id = :user_id", params! {
+        "user_id" => user_id
+    })?;
+
+    tx.exec_batch("INSERT INTO scopes (scope_name, user_id) VALUES (:scope_name, :user_id)", scopes.iter().map(|scope| params! {
+        "scope_name" => scope,
+        "user_id" => user_id
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::ScopesChanged, Some(user_id), Some(caller), Some(&format!("set to [{}]", scopes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::HttpResult;
+use crate::endpoints::run_blocking;
+use crate::check_token;
+
+#[derive(Serialize)]
+struct Response {
+    users:  Vec<String>,
+}
+
+/// List the IDs of all users holding the scope, either directly or through a role
+#[get("/scope/users/{scope_name}")]
+pub async fn users(data: web::Data<Arc<AppData>>, req: HttpRequest, scope_name: web::Path<String>) -> HttpResult {
+    check_token!(req, data);
+    let scope_name = scope_name.into_inner();
+
+    let user_ids: Vec<String> = run_blocking(&data, move |data| {
+        let mut conn = data.pool.get_conn()?;
+        let user_ids = conn.exec("SELECT user_id FROM scopes WHERE scope_name = :scope_name \
+            UNION \
+            SELECT user_roles.user_id FROM user_roles \
+            INNER JOIN role_scopes ON role_scopes.role_name = user_roles.role_name \
+            WHERE role_scopes.scope_name = :scope_name", params! {
+            "scope_name" => &scope_name
+        })?;
+
+        Ok(user_ids)
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(&Response { users: user_ids }))
+}
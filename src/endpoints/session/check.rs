@@ -1,7 +1,8 @@
 use std::sync::Arc;
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 use crate::env::AppData;
 use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
 use serde::Serialize;
 use mysql::{prelude::Queryable, Row, Params, params};
 use log::warn;
@@ -13,18 +14,27 @@ struct CheckResponse {
 }
 
 #[get("/session/check/{session_id}")]
-pub async fn check(data: web::Data<Arc<AppData>>, web::Path(session_id): web::Path<String>) -> HttpResult {
-    super::check_session(&data, &session_id)?;
+pub async fn check(data: web::Data<Arc<AppData>>, req: HttpRequest, session_id: web::Path<String>) -> HttpResult {
+    let api_token = super::optional_api_token(&req)?;
+    let session_id = session_id.into_inner();
+
+    let response = run_blocking(&data, move |data| check_session(data, api_token.as_deref(), &session_id)).await?;
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn check_session(data: &AppData, api_token: Option<&str>, session_id: &str) -> Result<CheckResponse, Error> {
+    super::check_session(data, api_token, session_id)?;
 
     let mut conn = data.pool.get_conn()?;
 
     let user_id: String = match conn.exec_first::<Row, &str, Params>("SELECT user_id FROM sessions WHERE session_id = :session_id", params! {
-        "session_id" => &session_id
+        "session_id" => session_id
     })? {
         Some(row) => {
             row.get("user_id").unwrap()
         },
-        None => unreachable!(), //Checked by the check_session call above,
+        // The session may have been deleted since it was checked above, e.g. by a logout or the sweeper
+        None => return Err(Error::NotFound("Session does not exist")),
     };
 
     match conn.exec_first::<Row, &str, Params>("SELECT active FROM users WHERE user_id = :user_id", params! {
@@ -34,15 +44,15 @@ pub async fn check(data: web::Data<Arc<AppData>>, web::Path(session_id): web::Pa
             let active: bool = row.get("active").unwrap();
 
             if !active {
-                Ok(HttpResponse::Ok().json(&CheckResponse { active: false, session_valid: false }))
+                Ok(CheckResponse { active: false, session_valid: false })
             } else {
-                Ok(HttpResponse::Ok().json(&CheckResponse { active: true, session_valid: true }))
+                Ok(CheckResponse { active: true, session_valid: true })
             }
         },
         None => {
-            warn!("Found stray session '{}' for nonexistent user '{}'!", &session_id, &user_id);
+            warn!("Found stray session '{}' for nonexistent user '{}'!", session_id, &user_id);
             conn.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
-                "session_id" => &session_id
+                "session_id" => session_id
             })?;
 
             Err(Error::Conflict("No user exists for provided session_id, but session exists."))
@@ -1,9 +1,10 @@
 use std::sync::Arc;
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 use mysql::{prelude::Queryable, Row, Params, params};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use crate::env::AppData;
 use crate::error::{HttpResult, Error};
+use crate::endpoints::run_blocking;
 use log::warn;
 
 #[derive(Serialize)]
@@ -14,31 +15,55 @@ struct DescribeResponse {
     name:       Option<String>,
     picture:    Option<String>,
     email:      Option<String>,
+    api_name:   Option<String>,
+    /// The user's effective scopes, only included if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scopes:     Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct DescribeQuery {
+    /// Include the user's effective scopes in the response
+    #[serde(default)]
+    include_scopes: bool,
+    /// Respond with a 403 if the user does not have this scope
+    require_scope:  Option<String>,
 }
 
 #[get("/session/describe/{session_id}")]
-pub async fn describe(data: web::Data<Arc<AppData>>, web::Path(session_id): web::Path<String>) -> HttpResult {
-    super::check_session(&data, &session_id)?;
+pub async fn describe(data: web::Data<Arc<AppData>>, req: HttpRequest, session_id: web::Path<String>, query: web::Query<DescribeQuery>) -> HttpResult {
+    let api_token = super::optional_api_token(&req)?;
+    let session_id = session_id.into_inner();
+    let query = query.into_inner();
+
+    let payload = run_blocking(&data, move |data| describe_session(data, api_token.as_deref(), &session_id, &query)).await?;
+    Ok(HttpResponse::Ok().json(&payload))
+}
+
+fn describe_session(data: &AppData, api_token: Option<&str>, session_id: &str, query: &DescribeQuery) -> Result<DescribeResponse, Error> {
+    super::check_session(data, api_token, session_id)?;
     let mut conn = data.pool.get_conn()?;
 
-    let row: Row = match conn.exec_first::<Row, &str, Params>("SELECT user_id,expiry FROM sessions WHERE session_id = :session_id", params! {
-        "session_id" => &session_id
+    let row: Row = match conn.exec_first::<Row, &str, Params>("SELECT user_id,expiry,api_name FROM sessions WHERE session_id = :session_id", params! {
+        "session_id" => session_id
     })? {
         Some(r) => r,
-        None => unreachable!(), // Unreachable, the call to super::check_session() above already checked if the session exists.
+        // The session may have been deleted since it was checked above, e.g. by a logout or the sweeper
+        None => return Err(Error::NotFound("Session does not exist")),
     };
 
     let user_id: String = row.get("user_id").unwrap();
     let expiry: i64 = row.get("expiry").unwrap();
+    let api_name: Option<String> = row.get("api_name").unwrap();
 
     let row: Row = match conn.exec_first::<Row, &str, Params>("SELECT active,name,email,picture FROM users WHERE user_id = :user_id", params! {
         "user_id" => &user_id
     })? {
         Some(r) => r,
         None => {
-            warn!("Found stray session '{}' for nonexistent user '{}'!", &session_id, &user_id);
+            warn!("Found stray session '{}' for nonexistent user '{}'!", session_id, &user_id);
             conn.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
-                "session_id" => &session_id
+                "session_id" => session_id
             })?;
 
             return Err(Error::Conflict("No user exists for provided session_id, but session exists."));
@@ -47,21 +72,38 @@ pub async fn describe(data: web::Data<Arc<AppData>>, web::Path(session_id): web:
 
     let active: bool = row.get("active").unwrap();
     if !active {
-        return Ok(HttpResponse::Ok().json(&DescribeResponse { active: false, user_id: None, expiry: None, name: None, picture: None, email: None }));
+        // An inactive user has no scopes at all
+        if query.require_scope.is_some() {
+            return Err(Error::Forbidden("The user does not have the required scope"));
+        }
+
+        return Ok(DescribeResponse { active: false, user_id: None, expiry: None, name: None, picture: None, email: None, api_name: None, scopes: None });
+    }
+
+    let scopes = if query.include_scopes || query.require_scope.is_some() {
+        Some(crate::endpoints::get_scopes(data, &user_id)?)
+    } else {
+        None
+    };
+
+    if let (Some(required), Some(user_scopes)) = (&query.require_scope, &scopes) {
+        if !user_scopes.contains(required) {
+            return Err(Error::Forbidden("The user does not have the required scope"));
+        }
     }
 
     let name: Option<String> = row.get("name").unwrap();
     let email: Option<String> = row.get("email").unwrap();
     let picture: Option<String> = row.get("picture").unwrap();
 
-    let payload = DescribeResponse {
+    Ok(DescribeResponse {
         active:     true,
         user_id:    Some(user_id),
         expiry:     Some(expiry),
         name,
         picture,
-        email
-    };
-
-    Ok(HttpResponse::Ok().json(&payload))
+        email,
+        api_name,
+        scopes: if query.include_scopes { scopes } else { None },
+    })
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params, params, TxOpts};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+
+#[post("/session/logout/{session_id}")]
+pub async fn logout(data: web::Data<Arc<AppData>>, session_id: web::Path<String>) -> HttpResult {
+    let session_id = session_id.into_inner();
+    run_blocking(&data, move |data| delete_session(data, &session_id)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn delete_session(data: &AppData, session_id: &str) -> Result<(), Error> {
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+
+    let row: Row = match tx.exec_first::<Row, &str, Params>("SELECT user_id,api_name FROM sessions WHERE session_id = :session_id", params! {
+        "session_id" => session_id
+    })? {
+        Some(r) => r,
+        None => return Err(Error::NotFound("Session does not exist")),
+    };
+
+    tx.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
+        "session_id" => session_id
+    })?;
+
+    let user_id: String = row.get("user_id").unwrap();
+    let api_name: Option<String> = row.get("api_name").unwrap();
+    audit::record(&mut tx, AuditEvent::SessionRevoked, Some(&user_id), api_name.as_deref(), Some("logged out"))?;
+
+    tx.commit()?;
+    Ok(())
+}
@@ -1,25 +1,65 @@
+use actix_web::HttpRequest;
 use crate::env::AppData;
-use mysql::{prelude::Queryable, Row, Params, params};
+use mysql::{prelude::Queryable, Row, Params, params, TxOpts};
+use serde::Serialize;
 use crate::error::Error;
+use crate::audit::{self, AuditEvent};
+use crate::jwt::{AccessTokenClaims, IdTokenClaims, ACCESS_TOKEN_TYPE, ID_TOKEN_TYPE};
 
 pub mod check;
 pub mod describe;
+pub mod logout;
+pub mod revoke;
+pub mod token;
 
-fn check_session(data: &AppData, session_id: &str) -> Result<(), Error> {
+/// Check if the session exists and has not expired. If it is valid, the idle expiry
+/// of the session is extended, but never past the session's absolute expiry.
+///
+/// If the request carries an API token, the session must have been issued for that API.
+pub fn check_session(data: &AppData, api_token: Option<&str>, session_id: &str) -> Result<(), Error> {
     let mut conn = data.pool.get_conn()?;
 
-    match conn.exec_first::<Row, &str, Params>("SELECT expiry FROM sessions WHERE session_id = :session_id", params! {
+    match conn.exec_first::<Row, &str, Params>("SELECT user_id,expiry,absolute_expiry,api_name FROM sessions WHERE session_id = :session_id", params! {
         "session_id" => &session_id
     })? {
         Some(session_row) => {
+            if let Some(api_token) = api_token {
+                let api_name = match crate::endpoints::get_api_name(data, api_token)? {
+                    Some(n) => n,
+                    None => return Err(Error::Unauthorized),
+                };
+
+                let session_api_name: Option<String> = session_row.get("api_name").unwrap();
+                if session_api_name.as_deref() != Some(&api_name) {
+                    return Err(Error::UnauthorizedMsg("The session was not issued for this API"));
+                }
+            }
+
             let expiry: i64 = session_row.get("expiry").unwrap();
-            if chrono::Utc::now().timestamp() >= expiry {
-                conn.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
+            let absolute_expiry: i64 = session_row.get::<Option<i64>, &str>("absolute_expiry").unwrap().unwrap_or(expiry);
+
+            let now = chrono::Utc::now().timestamp();
+            if now >= expiry || now >= absolute_expiry {
+                let mut tx = conn.start_transaction(TxOpts::default())?;
+                tx.exec_drop("DELETE FROM sessions WHERE session_id = :session_id", params! {
                     "session_id" => &session_id
                 })?;
 
+                let user_id: String = session_row.get("user_id").unwrap();
+                let session_api_name: Option<String> = session_row.get("api_name").unwrap();
+                audit::record(&mut tx, AuditEvent::SessionExpired, Some(&user_id), session_api_name.as_deref(), None)?;
+                tx.commit()?;
+
                 Err(Error::Unauthorized)
             } else {
+                let new_expiry = std::cmp::min(now + data.env.session_idle_timeout_secs, absolute_expiry);
+                if new_expiry > expiry {
+                    conn.exec_drop("UPDATE sessions SET expiry = :expiry WHERE session_id = :session_id", params! {
+                        "expiry" => new_expiry,
+                        "session_id" => &session_id
+                    })?;
+                }
+
                 Ok(())
             }
         },
@@ -27,4 +67,111 @@ fn check_session(data: &AppData, session_id: &str) -> Result<(), Error> {
             Err(Error::NotFound("Session does not exist"))
         },
     }
+}
+
+/// Get the API token from the request, if one was provided
+fn optional_api_token(req: &HttpRequest) -> Result<Option<String>, Error> {
+    match req.headers().get("authorization") {
+        Some(header) => match header.to_str() {
+            Ok(api_token) => Ok(Some(api_token.to_string())),
+            Err(_) => Err(Error::Unauthorized),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Delete every session belonging to the user, returning the amount of sessions that were revoked.
+/// `caller` is the name of the API revoking the sessions
+pub fn revoke_sessions<Q: Queryable>(conn: &mut Q, user_id: &str, caller: &str) -> Result<u64, Error> {
+    let revoked = conn.exec_iter("DELETE FROM sessions WHERE user_id = :user_id", params! {
+        "user_id" => user_id
+    })?.affected_rows();
+
+    if revoked > 0 {
+        audit::record(conn, AuditEvent::SessionRevoked, Some(user_id), Some(caller), Some(&format!("revoked {} sessions", revoked)))?;
+    }
+
+    Ok(revoked)
+}
+
+
+#[derive(Serialize)]
+pub struct IssuedTokens {
+    id_token:       String,
+    access_token:   String,
+    token_type:     &'static str,
+    expires_in:     i64,
+}
+
+/// Issue a signed ID token and access token for the user the session belongs to.
+/// The tokens never outlive the session's absolute expiry. `nonce` is the nonce the client provided when
+/// starting the login, it is only included in the ID token issued when the authorization code is exchanged.
+///
+/// The caller is responsible for checking that the session is valid
+pub fn issue_tokens(data: &AppData, session_id: &str, nonce: Option<&str>) -> Result<IssuedTokens, Error> {
+    let signer = data.signer()?;
+    let mut conn = data.pool.get_conn()?;
+
+    let session_row: Row = match conn.exec_first::<Row, &str, Params>("SELECT user_id,expiry,absolute_expiry,api_name FROM sessions WHERE session_id = :session_id", params! {
+        "session_id" => session_id
+    })? {
+        Some(r) => r,
+        None => return Err(Error::NotFound("Session does not exist")),
+    };
+
+    let user_id: String = session_row.get("user_id").unwrap();
+    let expiry: i64 = session_row.get("expiry").unwrap();
+    let absolute_expiry: i64 = session_row.get::<Option<i64>, &str>("absolute_expiry").unwrap().unwrap_or(expiry);
+    let api_name: String = match session_row.get::<Option<String>, &str>("api_name").unwrap() {
+        Some(n) => n,
+        None => return Err(Error::UnauthorizedMsg("The session was not issued for an API")),
+    };
+
+    let user_row: Row = match conn.exec_first::<Row, &str, Params>("SELECT active,name,email,picture FROM users WHERE user_id = :user_id", params! {
+        "user_id" => &user_id
+    })? {
+        Some(r) => r,
+        None => return Err(Error::Conflict("No user exists for provided session_id, but session exists.")),
+    };
+
+    let active: bool = user_row.get("active").unwrap();
+    if !active {
+        return Err(Error::Unauthorized);
+    }
+
+    let scopes = crate::endpoints::get_scopes(data, &user_id)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let exp = std::cmp::min(now + data.env.jwt_lifetime_secs, absolute_expiry);
+
+    let id_token = signer.sign(ID_TOKEN_TYPE, &IdTokenClaims {
+        iss:        data.env.host.clone(),
+        sub:        user_id.clone(),
+        aud:        api_name.clone(),
+        exp,
+        iat:        now,
+        name:       user_row.get("name").unwrap(),
+        email:      user_row.get("email").unwrap(),
+        picture:    user_row.get("picture").unwrap(),
+        nonce:      nonce.map(String::from),
+    })?;
+
+    let access_token = signer.sign(ACCESS_TOKEN_TYPE, &AccessTokenClaims {
+        iss:        data.env.host.clone(),
+        sub:        user_id.clone(),
+        aud:        api_name.clone(),
+        exp,
+        iat:        now,
+        client_id:  api_name.clone(),
+        scope:      scopes.join(" "),
+    })?;
+
+    audit::record(&mut conn, AuditEvent::TokenIssued, Some(&user_id), Some(&api_name), Some("ID token and access token"))?;
+
+    Ok(IssuedTokens {
+        id_token,
+        access_token,
+        token_type: "Bearer",
+        expires_in: exp - now,
+    })
 }
\ No newline at end of file
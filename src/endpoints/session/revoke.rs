@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::check_token;
+
+#[derive(Serialize)]
+struct RevokeResponse {
+    revoked:    u64,
+}
+
+#[post("/session/revoke/{user_id}")]
+pub async fn revoke(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>) -> HttpResult {
+    let caller = check_token!(req, data);
+    let user_id = user_id.into_inner();
+
+    let revoked = run_blocking(&data, move |data| {
+        if !crate::endpoints::user_exists(data, &user_id)? {
+            return Err(Error::NotFound("The requested user does not exist"));
+        }
+
+        let mut conn = data.pool.get_conn()?;
+        super::revoke_sessions(&mut conn, &user_id, &caller)
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(&RevokeResponse { revoked }))
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use crate::env::AppData;
+use crate::error::HttpResult;
+use crate::endpoints::run_blocking;
+use crate::authorization;
+
+/// Exchange a session for a signed ID token and access token, which downstream services can validate offline.
+/// The session must have been issued for the API making the request.
+#[post("/session/token/{session_id}")]
+pub async fn token(data: web::Data<Arc<AppData>>, req: HttpRequest, session_id: web::Path<String>) -> HttpResult {
+    let api_token = authorization!(req);
+    let session_id = session_id.into_inner();
+
+    let tokens = run_blocking(&data, move |data| {
+        super::check_session(data, Some(&api_token), &session_id)?;
+        super::issue_tokens(data, &session_id, None)
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(&tokens))
+}
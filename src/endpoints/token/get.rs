@@ -1,10 +1,13 @@
 use std::sync::Arc;
 use actix_web::{get, web, HttpRequest, HttpResponse};
-use mysql::{Row, params};
+use mysql::{Row, params, TxOpts};
 use mysql::prelude::Queryable;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use crate::env::AppData;
 use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::apis::provider::DEFAULT_PROVIDER;
 use crate::check_token;
 use log::warn;
 
@@ -15,37 +18,108 @@ struct TokenResponse<'a> {
     active:       bool
 }
 
-#[get("/token/get/{user_id}")]
-pub async fn get(data: web::Data<Arc<AppData>>, req: HttpRequest, web::Path(user_id): web::Path<String>) -> HttpResult {
-    check_token!(req, data);
-    let mut conn = data.pool.get_conn()?;
+#[derive(Deserialize)]
+pub struct GetQuery {
+    #[serde(default)]
+    force_refresh:  bool,
+}
 
-    let refresh_token: Row = match conn.exec_first("SELECT refresh_token FROM users WHERE user_id = :user_id", params! {
-        "user_id" => &user_id
-    })? {
-        Some(r) => r,
-        None => return Err(Error::NotFound("The requested user does not exist")),
-    };
+enum StoredToken {
+    /// The user is not active, so no access token is handed out
+    Inactive,
+    /// A cached access token which is valid for long enough to hand out
+    Cached { access_token: String, expiry: i64 },
+    /// No usable access token is cached, the refresh token has to be exchanged with the user's provider for a new one
+    Refresh { refresh_token: String, provider: String },
+}
 
-    let refresh_token = match refresh_token.get::<Option<String>, &str>("refresh_token").unwrap() {
-        Some(rt) => rt,
-        None => {
-            warn!("Found user '{}' without refresh_token!", &user_id);
-            conn.exec_drop("UPDATE users SET active = false WHERE user_id = :user_id", params! {
-                "user_id" => &user_id
-            })?;
+// A cached access token is not handed out if it expires within this many seconds
+const ACCESS_TOKEN_EXPIRY_MARGIN_SECS: i64 = 300;
 
-            return Err(Error::Conflict("Internal conflict"));
-        }
+#[get("/token/get/{user_id}")]
+pub async fn get(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, query: web::Query<GetQuery>) -> HttpResult {
+    let caller = check_token!(req, data);
+    let user_id = user_id.into_inner();
+    let force_refresh = query.force_refresh;
+
+    let lookup_user_id = user_id.clone();
+    let lookup_caller = caller.clone();
+    let (refresh_token, provider) = match run_blocking(&data, move |data| get_stored_token(data, &lookup_caller, &lookup_user_id, force_refresh)).await? {
+        StoredToken::Inactive => {
+            return Ok(HttpResponse::Ok().json(&TokenResponse { access_token: None, expiry: None, active: false }));
+        },
+        StoredToken::Cached { access_token, expiry } => {
+            return Ok(HttpResponse::Ok().json(&TokenResponse { access_token: Some(&access_token), expiry: Some(expiry), active: true }));
+        },
+        StoredToken::Refresh { refresh_token, provider } => (refresh_token, provider),
     };
 
-    let refresh_response = crate::apis::google_auth::refresh_token(&data.env, &refresh_token)?;
+    let provider = data.provider(&provider)?;
+    let timer = data.metrics.time_provider_call(provider.name(), "refresh_token");
+    let refresh_response = provider.refresh_token(&refresh_token).await?;
+    timer.observe_duration();
+    let expiry = chrono::Utc::now().timestamp() + refresh_response.expires_in;
+
+    let access_token = refresh_response.access_token.clone();
+    run_blocking(&data, move |data| {
+        let mut conn = data.pool.get_conn()?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        super::cache_access_token(&mut tx, &user_id, &access_token, expiry)?;
+        audit::record(&mut tx, AuditEvent::TokenIssued, Some(&user_id), Some(&caller), Some("refreshed access token"))?;
+        tx.commit()?;
+        Ok(())
+    }).await?;
 
     let response = TokenResponse {
         access_token:   Some(&refresh_response.access_token),
-        expiry:         Some(chrono::Utc::now().timestamp() + refresh_response.expires_in),
+        expiry:         Some(expiry),
         active:         true
     };
 
     Ok(HttpResponse::Ok().json(&response))
+}
+
+fn get_stored_token(data: &AppData, caller: &str, user_id: &str, force_refresh: bool) -> Result<StoredToken, Error> {
+    let mut conn = data.pool.get_conn()?;
+
+    let user_row: Row = match conn.exec_first("SELECT users.active,users.invited,users.refresh_token,users.provider,access_tokens.access_token,access_tokens.expiry FROM users LEFT JOIN access_tokens ON users.user_id = access_tokens.user_id WHERE users.user_id = :user_id", params! {
+        "user_id" => user_id
+    })? {
+        Some(r) => r,
+        None => return Err(Error::NotFound("The requested user does not exist")),
+    };
+
+    let active: bool = user_row.get("active").unwrap();
+    if !active {
+        return Ok(StoredToken::Inactive);
+    }
+
+    if !force_refresh {
+        let access_token: Option<String> = user_row.get("access_token").unwrap();
+        let expiry: Option<i64> = user_row.get("expiry").unwrap();
+
+        if let (Some(access_token), Some(expiry)) = (access_token, expiry) {
+            if chrono::Utc::now().timestamp() + ACCESS_TOKEN_EXPIRY_MARGIN_SECS < expiry {
+                return Ok(StoredToken::Cached { access_token, expiry });
+            }
+        }
+    }
+
+    let provider = user_row.get::<Option<String>, &str>("provider").unwrap().unwrap_or_else(|| DEFAULT_PROVIDER.to_string());
+    match user_row.get::<Option<String>, &str>("refresh_token").unwrap() {
+        Some(refresh_token) => Ok(StoredToken::Refresh { refresh_token, provider }),
+        // Invited users have no refresh token until they log in for the first time, which is not a conflict
+        None if user_row.get::<bool, &str>("invited").unwrap() => Err(Error::NotFound("The user has not logged in yet")),
+        None => {
+            warn!("Found user '{}' without refresh_token!", user_id);
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            tx.exec_drop("UPDATE users SET active = false WHERE user_id = :user_id", params! {
+                "user_id" => user_id
+            })?;
+            audit::record(&mut tx, AuditEvent::UserDeactivated, Some(user_id), Some(caller), Some("no refresh token stored"))?;
+            tx.commit()?;
+
+            Err(Error::Conflict("Internal conflict"))
+        }
+    }
 }
\ No newline at end of file
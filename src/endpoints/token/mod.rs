@@ -1,2 +1,13 @@
+use mysql::{prelude::Queryable, params};
+
 pub mod get;
 
+/// Store the access token for the user, so it can be served by `token::get` until it is about to expire
+pub fn cache_access_token<Q: Queryable>(conn: &mut Q, user_id: &str, access_token: &str, expiry: i64) -> mysql::Result<()> {
+    conn.exec_drop("REPLACE INTO access_tokens (user_id, access_token, expiry) VALUES (:user_id, :access_token, :expiry)", params! {
+        "user_id" => user_id,
+        "access_token" => access_token,
+        "expiry" => expiry
+    })
+}
+
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[post("/user/activate/{user_id}")]
+pub async fn activate(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let user_id = user_id.into_inner();
+
+    run_blocking(&data, move |data| activate_user(data, &caller, &user_id)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn activate_user(data: &AppData, caller: &str, user_id: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    tx.exec_drop("UPDATE users SET active = true WHERE user_id = :user_id", params! {
+        "user_id" => user_id
+    })?;
+
+    audit::record(&mut tx, AuditEvent::UserActivated, Some(user_id), Some(caller), None)?;
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[derive(Serialize)]
+struct DeactivateResponse {
+    /// The amount of sessions that were revoked
+    revoked:    u64,
+}
+
+#[post("/user/deactivate/{user_id}")]
+pub async fn deactivate(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let user_id = user_id.into_inner();
+
+    let revoked = run_blocking(&data, move |data| deactivate_user(data, &caller, &user_id)).await?;
+    Ok(HttpResponse::Ok().json(&DeactivateResponse { revoked }))
+}
+
+/// Deactivate the user, and revoke all their sessions and their cached access token so they are logged out everywhere
+fn deactivate_user(data: &AppData, caller: &str, user_id: &str) -> Result<u64, Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for query in [
+        "UPDATE users SET active = false WHERE user_id = :user_id",
+        "DELETE authorization_codes FROM authorization_codes JOIN sessions ON authorization_codes.session_id = sessions.session_id WHERE sessions.user_id = :user_id",
+        "DELETE FROM access_tokens WHERE user_id = :user_id",
+    ] {
+        tx.exec_drop(query, params! {
+            "user_id" => user_id
+        })?;
+    }
+
+    audit::record(&mut tx, AuditEvent::UserDeactivated, Some(user_id), Some(caller), None)?;
+    let revoked = crate::endpoints::session::revoke_sessions(&mut tx, user_id, caller)?;
+    tx.commit()?;
+
+    Ok(revoked)
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, params, TxOpts};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+#[post("/user/delete/{user_id}")]
+pub async fn delete(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let user_id = user_id.into_inner();
+
+    run_blocking(&data, move |data| delete_user(data, &caller, &user_id)).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Delete the user, along with everything else stored about them
+fn delete_user(data: &AppData, caller: &str, user_id: &str) -> Result<(), Error> {
+    if !crate::endpoints::user_exists(data, user_id)? {
+        return Err(Error::NotFound("The requested user does not exist"));
+    }
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for query in [
+        "DELETE authorization_codes FROM authorization_codes JOIN sessions ON authorization_codes.session_id = sessions.session_id WHERE sessions.user_id = :user_id",
+        "DELETE FROM sessions WHERE user_id = :user_id",
+        "DELETE FROM access_tokens WHERE user_id = :user_id",
+        "DELETE FROM scopes WHERE user_id = :user_id",
+        "DELETE FROM user_roles WHERE user_id = :user_id",
+        "DELETE FROM users WHERE user_id = :user_id",
+    ] {
+        tx.exec_drop(query, params! {
+            "user_id" => user_id
+        })?;
+    }
+
+    audit::record(&mut tx, AuditEvent::UserDeleted, Some(user_id), Some(caller), None)?;
+    tx.commit()?;
+
+    Ok(())
+}
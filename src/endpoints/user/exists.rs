@@ -1,9 +1,9 @@
 use std::sync::Arc;
-use actix_web::{web, get, HttpResponse};
-use mysql::prelude::Queryable;
-use mysql::{Row, params};
+use actix_web::{web, get, HttpRequest, HttpResponse};
 use crate::env::AppData;
 use crate::error::HttpResult;
+use crate::endpoints::run_blocking;
+use crate::check_token;
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -12,11 +12,30 @@ struct Response {
 }
 
 #[get("/user/exists/{user_id}")]
-pub async fn exists(data: web::Data<Arc<AppData>>, web::Path(user_id): web::Path<String>) -> HttpResult {
-    let mut conn = data.pool.get_conn()?;
-    let row: Option<Row> = conn.exec_first("SELECT 1 FROM users WHERE user_id = :user_id", params! {
-        "user_id" => &user_id
-    })?;
+pub async fn exists(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>) -> HttpResult {
+    if !data.env.allow_unauthenticated_user_lookup {
+        check_token!(req, data);
+    }
 
-    Ok(HttpResponse::Ok().json(&Response { exists: row.is_some() }))
-}
\ No newline at end of file
+    let user_id = user_id.into_inner();
+    let user_exists = run_blocking(&data, move |data| Ok(crate::endpoints::user_exists(data, &user_id)?)).await?;
+
+    Ok(HttpResponse::Ok().json(&Response { exists: user_exists }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use crate::tests::get_without_database;
+
+    #[actix_web::test]
+    async fn requires_api_token() {
+        assert_eq!(get_without_database(super::exists, "/user/exists/1234", &[]).await, (StatusCode::UNAUTHORIZED, 0));
+    }
+
+    #[actix_web::test]
+    async fn allows_unauthenticated_lookup_when_configured() {
+        let response = get_without_database(super::exists, "/user/exists/1234", &[("ALLOW_UNAUTHENTICATED_USER_LOOKUP", "true")]).await;
+        assert_eq!(response, (StatusCode::INTERNAL_SERVER_ERROR, 1));
+    }
+}
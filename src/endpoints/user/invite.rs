@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mysql::{prelude::Queryable, Row, Params, params, TxOpts};
+use serde::{Serialize, Deserialize};
+use crate::env::AppData;
+use crate::error::{Error, HttpResult};
+use crate::endpoints::run_blocking;
+use crate::audit::{self, AuditEvent};
+use crate::check_admin_token;
+
+/// The MySQL error code for a duplicate key
+const ER_DUP_ENTRY: u16 = 1062;
+
+#[derive(Deserialize)]
+pub struct InviteRequest {
+    email:  String,
+    name:   Option<String>,
+    #[serde(default)]
+    scopes: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct InviteResponse {
+    /// The ID of the pre-provisioned user. It can be used like any other user ID until the user logs in,
+    /// after which it is replaced by their real user ID
+    user_id:    String,
+}
+
+/// Pre-provision a user by their email address. The first time someone with this verified email address
+/// logs in, they take over this user, including its scopes and roles.
+#[post("/user/invite")]
+pub async fn invite(data: web::Data<Arc<AppData>>, req: HttpRequest, payload: web::Json<InviteRequest>) -> HttpResult {
+    let caller = check_admin_token!(req, data);
+    let mut payload = payload.into_inner();
+
+    // The user ID is derived from the email address, and has to fit in the user_id column
+    if payload.email.len() > 200 || payload.email.split_once('@').is_none() {
+        return Err(Error::BadRequest("The provided email address is not valid"));
+    }
+
+    for scope in &payload.scopes {
+        crate::endpoints::scope::validate_scope_name(scope)?;
+    }
+
+    payload.scopes.sort();
+    payload.scopes.dedup();
+
+    let user_id = run_blocking(&data, move |data| invite_user(data, &caller, payload)).await?;
+    Ok(HttpResponse::Ok().json(&InviteResponse { user_id }))
+}
+
+fn invite_user(data: &AppData, caller: &str, payload: InviteRequest) -> Result<String, Error> {
+    let email = payload.email.to_lowercase();
+    let user_id = format!("invitation:{}", email);
+
+    let mut conn = data.pool.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    if tx.exec_first::<Row, &str, Params>("SELECT user_id FROM users WHERE email = :email FOR UPDATE", params! {
+        "email" => &email
+    })?.is_some() {
+        return Err(Error::Conflict("A user with this email address already exists"));
+    }
+
+    // The lock doesn't cover rows which don't exist yet, so a concurrent invitation may still win the race for the user ID
+    match tx.exec_drop("INSERT INTO users (user_id, active, name, email, invited) VALUES (:user_id, true, :name, :email, true)", params! {
+        "user_id" => &user_id,
+        "name" => &payload.name,
+        "email" => &email
+    }) {
+        Err(mysql::Error::MySqlError(e)) if e.code == ER_DUP_ENTRY => return Err(Error::Conflict("A user with this email address already exists")),
+        r => r?,
+    }
+
+    tx.exec_batch("INSERT INTO scopes (scope_name, user_id) VALUES (:scope_name, :user_id)", payload.scopes.iter().map(|scope| params! {
+        "scope_name" => scope,
+        "user_id" => &user_id
+    }))?;
+
+    audit::record(&mut tx, AuditEvent::UserInvited, Some(&user_id), Some(caller), Some(&format!("with [{}]", payload.scopes.join(", "))))?;
+    tx.commit()?;
+
+    Ok(user_id)
+}
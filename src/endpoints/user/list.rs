@@ -1,47 +1,161 @@
 use std::sync::Arc;
 use actix_web::{web, get, HttpResponse, HttpRequest};
 use mysql::prelude::Queryable;
-use mysql::{Row, Params, PooledConn};
+use mysql::{Row, Params, PooledConn, Value};
 use crate::env::AppData;
 use crate::error::{HttpResult, Error};
+use crate::endpoints::run_blocking;
 use crate::check_token;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
+
+const DEFAULT_LIMIT: u64 = 50;
+const MAX_LIMIT: u64 = 500;
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    offset:         u64,
+    /// The maximum amount of users to return. Defaults to 50, and is capped at 500
+    limit:          Option<u64>,
+    active:         Option<bool>,
+    /// Only include users whose email address is in this domain, e.g. `example.com`
+    email_domain:   Option<String>,
+    /// Only include users with this scope, either directly or through a role
+    scope:          Option<String>,
+    /// Only include users whose name or email address contains this text
+    search:         Option<String>,
+    #[serde(default)]
+    sort:           SortField,
+    #[serde(default)]
+    order:          SortOrder,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum SortField {
+    Id,
+    #[default]
+    Name,
+    Email,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
 
 #[derive(Serialize)]
 struct Response {
     users:  Vec<User>,
+    /// The total amount of users matching the filters, regardless of the offset and limit
+    total:  u64,
 }
 
 #[derive(Serialize)]
 struct User {
-    id:     String,
-    name:   String,
-    email:  String,
+    id:         String,
+    name:       Option<String>,
+    email:      Option<String>,
+    picture:    Option<String>,
+    active:     bool,
+    /// The user was invited, but has not logged in yet
+    invited:    bool,
 }
 
 #[get("/user/list")]
-pub async fn list(data: web::Data<Arc<AppData>>, req: HttpRequest) -> HttpResult {
+pub async fn list(data: web::Data<Arc<AppData>>, req: HttpRequest, query: web::Query<ListQuery>) -> HttpResult {
     check_token!(req, data);
-    let mut conn = data.pool.get_conn()?;
-    let users = get_users(&mut conn)?;
-    let response = Response {
-        users
-    };
+    let query = query.into_inner();
+    let response = run_blocking(&data, move |data| {
+        let mut conn = data.pool.get_conn()?;
+        get_users(&mut conn, &query)
+    }).await?;
 
     Ok(HttpResponse::Ok().json(&response))
 }
 
-fn get_users(conn: &mut PooledConn) -> Result<Vec<User>, Error> {
-    let rows: Vec<Row> = conn.exec("SELECT user_id,name,email FROM users", Params::Empty)?;
+fn get_users(conn: &mut PooledConn, query: &ListQuery) -> Result<Response, Error> {
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<(&str, Value)> = Vec::new();
+
+    if let Some(active) = query.active {
+        conditions.push("active = :active");
+        params.push(("active", active.into()));
+    }
+
+    if let Some(email_domain) = &query.email_domain {
+        conditions.push("email LIKE :email_domain");
+        params.push(("email_domain", format!("%@{}", escape_like(email_domain)).into()));
+    }
+
+    if let Some(scope) = &query.scope {
+        conditions.push("user_id IN (SELECT user_id FROM scopes WHERE scope_name = :scope UNION SELECT user_roles.user_id FROM user_roles JOIN role_scopes ON user_roles.role_name = role_scopes.role_name WHERE role_scopes.scope_name = :scope)");
+        params.push(("scope", scope.as_str().into()));
+    }
+
+    if let Some(search) = &query.search {
+        conditions.push("(name LIKE :search OR email LIKE :search)");
+        params.push(("search", format!("%{}%", escape_like(search)).into()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let to_params = |params: &[(&str, Value)]| if params.is_empty() {
+        Params::Empty
+    } else {
+        Params::from(params.to_vec())
+    };
+
+    let total: u64 = conn.exec_first(format!("SELECT COUNT(*) FROM users {}", where_clause), to_params(&params))?.unwrap_or(0);
+
+    let sort_column = match query.sort {
+        SortField::Id => "user_id",
+        SortField::Name => "name",
+        SortField::Email => "email",
+    };
+
+    let sort_order = match query.order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    params.push(("limit", limit.into()));
+    params.push(("offset", query.offset.into()));
+
+    // The user ID is added to the ordering so that pages are stable when the sort column has duplicates
+    let rows: Vec<Row> = conn.exec(
+        format!("SELECT user_id,name,email,picture,active,invited FROM users {} ORDER BY {} {}, user_id LIMIT :limit OFFSET :offset", where_clause, sort_column, sort_order),
+        to_params(&params)
+    )?;
+
     let users: Vec<_> = rows.into_iter()
         .map(|f| {
             User {
                 id: f.get("user_id").unwrap(),
                 name: f.get("name").unwrap(),
-                email: f.get("email").unwrap()
+                email: f.get("email").unwrap(),
+                picture: f.get("picture").unwrap(),
+                active: f.get("active").unwrap(),
+                invited: f.get("invited").unwrap(),
             }
         })
         .collect();
 
-    Ok(users)
-}
\ No newline at end of file
+    Ok(Response {
+        users,
+        total,
+    })
+}
+
+/// Escape the wildcard characters of a LIKE pattern, so user input is matched literally
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
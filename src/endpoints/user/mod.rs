@@ -1,4 +1,8 @@
 pub mod scopes;
 pub mod describe;
 pub mod exists;
-pub mod list;
\ No newline at end of file
+pub mod list;
+pub mod activate;
+pub mod deactivate;
+pub mod delete;
+pub mod invite;
\ No newline at end of file
@@ -1,23 +1,45 @@
 use std::sync::Arc;
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 use mysql::{Row, params};
 use mysql::prelude::Queryable;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use crate::env::AppData;
 use crate::error::{Error, HttpResult};
+use crate::endpoints::{run_blocking, ScopeSource};
+use crate::check_token;
+
+#[derive(Deserialize)]
+pub struct ScopesQuery {
+    #[serde(default)]
+    include_sources:    bool,
+}
 
 #[derive(Serialize)]
 struct ScopesResponse {
     scopes:     Vec<String>,
-    is_active:  bool
+    is_active:  bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources:    Option<Vec<ScopeSource>>,
 }
 
 #[get("/user/scopes/{user_id}")]
-pub async fn scopes(data: web::Data<Arc<AppData>>, web::Path(user_id): web::Path<String>) -> HttpResult {
+pub async fn scopes(data: web::Data<Arc<AppData>>, req: HttpRequest, user_id: web::Path<String>, query: web::Query<ScopesQuery>) -> HttpResult {
+    if !data.env.allow_unauthenticated_user_lookup {
+        check_token!(req, data);
+    }
+
+    let user_id = user_id.into_inner();
+    let include_sources = query.include_sources;
+
+    let response = run_blocking(&data, move |data| get_scopes(data, &user_id, include_sources)).await?;
+    Ok(HttpResponse::Ok().json(&response))
+}
+
+fn get_scopes(data: &AppData, user_id: &str, include_sources: bool) -> Result<ScopesResponse, Error> {
     let mut conn = data.pool.get_conn()?;
 
     let row: Row = match conn.exec_first("SELECT active FROM users WHERE user_id = :user_id", params! {
-        "user_id" => &user_id
+        "user_id" => user_id
     })? {
         Some(r) => r,
         None => return Err(Error::NotFound("The requested user does not exist")),
@@ -26,10 +48,32 @@ pub async fn scopes(data: web::Data<Arc<AppData>>, web::Path(user_id): web::Path
     let active: bool = row.get("active").unwrap();
 
     if !active {
-        return Ok(HttpResponse::Ok().json(&ScopesResponse { scopes: vec![], is_active: false }));
+        return Ok(ScopesResponse { scopes: vec![], is_active: false, sources: None });
     }
 
-    let scopes = crate::endpoints::get_scopes(&data, &user_id)?;
+    let user_scopes = crate::endpoints::get_scopes(data, user_id)?;
+    let sources = if include_sources {
+        Some(crate::endpoints::get_scope_sources(data, user_id)?)
+    } else {
+        None
+    };
+
+    Ok(ScopesResponse { scopes: user_scopes, is_active: true, sources })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use crate::tests::get_without_database;
 
-    Ok(HttpResponse::Ok().json(&ScopesResponse { scopes, is_active: true}))
+    #[actix_web::test]
+    async fn requires_api_token() {
+        assert_eq!(get_without_database(super::scopes, "/user/scopes/1234", &[]).await, (StatusCode::UNAUTHORIZED, 0));
+    }
+
+    #[actix_web::test]
+    async fn allows_unauthenticated_lookup_when_configured() {
+        let response = get_without_database(super::scopes, "/user/scopes/1234", &[("ALLOW_UNAUTHENTICATED_USER_LOOKUP", "true")]).await;
+        assert_eq!(response, (StatusCode::INTERNAL_SERVER_ERROR, 1));
+    }
 }
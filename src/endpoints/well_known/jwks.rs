@@ -0,0 +1,17 @@
+use std::sync::Arc;
+use actix_web::{get, web, HttpResponse};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::HttpResult;
+use crate::jwt::PublicJwk;
+
+#[derive(Serialize)]
+struct JwksResponse<'a> {
+    keys:   [&'a PublicJwk; 1],
+}
+
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(data: web::Data<Arc<AppData>>) -> HttpResult {
+    let signer = data.signer()?;
+    Ok(HttpResponse::Ok().json(&JwksResponse { keys: [signer.jwk()] }))
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use actix_web::{get, web, HttpResponse};
+use serde::Serialize;
+use crate::env::AppData;
+use crate::error::HttpResult;
+
+/// Only what is needed to validate the tokens we issue is advertised. /oauth2/login and /oauth2/token
+/// are not OpenID Connect compliant, so OpenID Connect clients must not be pointed at them
+#[derive(Serialize)]
+struct DiscoveryDocument {
+    issuer:                                 String,
+    jwks_uri:                               String,
+    id_token_signing_alg_values_supported:  &'static [&'static str],
+}
+
+#[get("/.well-known/openid-configuration")]
+pub async fn openid_configuration(data: web::Data<Arc<AppData>>) -> HttpResult {
+    // Only advertise ourselves as an OpenID provider if we are able to sign tokens
+    data.signer()?;
+
+    let host = &data.env.host;
+    Ok(HttpResponse::Ok().json(&DiscoveryDocument {
+        issuer:                                 host.clone(),
+        jwks_uri:                               format!("{}/.well-known/jwks.json", host),
+        id_token_signing_alg_values_supported:  &["RS256"],
+    }))
+}
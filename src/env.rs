@@ -1,6 +1,15 @@
 use serde::Deserialize;
-use mysql::OptsBuilder;
+use mysql::{OptsBuilder, TxOpts};
 use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::apis::google_auth::GoogleProvider;
+use crate::db::Pool;
+use crate::apis::oidc::{OidcProvider, OidcProviderConfig};
+use crate::apis::provider::IdentityProvider;
+use crate::error::Error;
+use crate::jwt::JwtSigner;
+use crate::metrics::Metrics;
 
 #[derive(Clone, Deserialize)]
 pub struct Env {
@@ -11,41 +20,209 @@ pub struct Env {
     pub google_client_id:       String,
     pub google_client_secret:   String,
     pub host:                   String,
+    /// Overrides Google's `/.well-known/openid-configuration` URL, e.g. to point at a mock issuer
+    pub google_discovery_url:   Option<String>,
+    /// Overrides the JWKS URI from Google's discovery document
+    pub google_jwks_uri:        Option<String>,
+    /// Path to a JSON file listing additional OpenID Connect providers
+    pub identity_providers_config:  Option<String>,
+    /// The maximum lifetime of a session, regardless of activity
+    #[serde(default = "default_session_absolute_timeout")]
+    pub session_absolute_timeout_secs:  i64,
+    /// The time after which a session expires if it is not used
+    #[serde(default = "default_session_idle_timeout")]
+    pub session_idle_timeout_secs:      i64,
+    /// Allow /user/exists and /user/scopes to be called without an API token, as they could before.
+    /// Only meant to give consumers time to start sending their API token
+    #[serde(default)]
+    pub allow_unauthenticated_user_lookup:  bool,
+    /// Hosted domains (the `hd` claim) of which users may sign in. Comma separated
+    #[serde(default)]
+    pub allowed_hosted_domains:         Vec<String>,
+    /// Email domains of which users may sign in, their email address must be verified. Comma separated.
+    /// If neither this nor `allowed_hosted_domains` is set, users of any domain may sign in
+    #[serde(default)]
+    pub allowed_email_domains:          Vec<String>,
+    /// What to do with users signing in for the first time
+    #[serde(default)]
+    pub new_user_policy:                NewUserPolicy,
+    /// The time within which a login started with /oauth2/login must be completed
+    #[serde(default = "default_state_ttl")]
+    pub state_ttl_secs:                 i64,
+    /// The interval at which expired states, sessions and authorization codes are removed from the database
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval_secs:            u64,
+    /// The time within which an authorization code handed out by /oauth2/grant must be exchanged
+    #[serde(default = "default_authorization_code_lifetime")]
+    pub authorization_code_lifetime_secs:   i64,
+    /// Path to the PEM encoded RSA private key used to sign ID- and access tokens.
+    /// If not set, Authlander does not issue tokens
+    pub jwt_signing_key:                Option<String>,
+    /// The lifetime of issued ID- and access tokens
+    #[serde(default = "default_jwt_lifetime")]
+    pub jwt_lifetime_secs:              i64,
+    /// If no API has admin rights yet, an admin API named `admin` is created with this token on startup.
+    /// Admin APIs can manage the other APIs, so this is how the first one is created
+    pub bootstrap_admin_token:          Option<String>,
+    /// The address on which /metrics is served, e.g. `127.0.0.1:9090`. It is kept off the public listener,
+    /// so it can be firewalled separately. If not set, metrics are not served
+    pub metrics_address:                Option<String>,
+}
+
+#[derive(Clone, Copy, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NewUserPolicy {
+    /// New users are created as active, and can use Authlander immediately
+    #[default]
+    Activate,
+    /// New users are created as inactive, and have to be activated with /user/activate first
+    Pending,
+    /// Only users which already exist may sign in
+    Reject,
+}
+
+// 7 days
+fn default_session_absolute_timeout() -> i64 {
+    604_800
+}
+
+// 1 day
+fn default_session_idle_timeout() -> i64 {
+    86_400
+}
+
+// 10 minutes
+fn default_state_ttl() -> i64 {
+    600
+}
+
+// 5 minutes
+fn default_sweep_interval() -> u64 {
+    300
+}
+
+// 1 minute
+fn default_authorization_code_lifetime() -> i64 {
+    60
+}
+
+// 1 hour
+fn default_jwt_lifetime() -> i64 {
+    3_600
 }
 
 pub struct AppData {
-    pub pool:   mysql::Pool,
-    pub env:    Env,
-    pub tera:   tera::Tera,
+    pub pool:       Pool,
+    pub env:        Env,
+    pub tera:       tera::Tera,
+    pub providers:  HashMap<String, Box<dyn IdentityProvider>>,
+    pub signer:     Option<JwtSigner>,
+    pub metrics:    Metrics,
 }
 
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+/// The name of the admin API created from `bootstrap_admin_token`
+const BOOTSTRAP_ADMIN_NAME: &str = "admin";
+
 mod migrations {
     use refinery::embed_migrations;
     embed_migrations!("./migrations");
 }
 
+impl Env {
+    fn mysql_options(&self) -> OptsBuilder {
+        OptsBuilder::new()
+            .ip_or_hostname(Some(&self.mysql_host))
+            .user(Some(&self.mysql_username))
+            .pass(Some(&self.mysql_password))
+            .db_name(Some(&self.mysql_database))
+    }
+}
+
+fn templates() -> Result<tera::Tera> {
+    let mut tera = tera::Tera::new("templates/**/*")?;
+    tera.autoescape_on(vec![]);
+    Ok(tera)
+}
+
 impl AppData {
-    pub fn new(env: &Env) -> Result<Self> {
-        let options = OptsBuilder::new()
-            .ip_or_hostname(Some(&env.mysql_host))
-            .user(Some(&env.mysql_username))
-            .pass(Some(&env.mysql_password))
-            .db_name(Some(&env.mysql_database));
-        let pool = mysql::Pool::new(options)?;
+    pub async fn new(env: &Env) -> Result<Self> {
+        let metrics = Metrics::new()?;
+        let pool = Pool::new(mysql::Pool::new(env.mysql_options())?, metrics.db_connection_wait.clone());
+        let tera = templates()?;
 
-        let mut tera = tera::Tera::new("templates/**/*")?;
-        tera.autoescape_on(vec![]);
+        // A single client is shared by all identity providers, so connections are reused
+        let http = reqwest::Client::builder()
+            .connect_timeout(HTTP_CONNECT_TIMEOUT)
+            .timeout(HTTP_TIMEOUT)
+            .build()?;
+
+        let mut providers: HashMap<String, Box<dyn IdentityProvider>> = HashMap::new();
+        let google = GoogleProvider::discover(env, http.clone()).await?;
+        providers.insert(google.name().to_string(), Box::new(google));
+
+        if let Some(path) = &env.identity_providers_config {
+            let configs: Vec<OidcProviderConfig> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            for config in configs {
+                let provider = OidcProvider::discover(config, http.clone()).await?;
+                providers.insert(provider.name().to_string(), Box::new(provider));
+            }
+        }
+
+        let signer = match &env.jwt_signing_key {
+            Some(path) => Some(JwtSigner::from_pem_file(path)?),
+            None => None,
+        };
 
         Ok(Self {
             pool,
             env: env.clone(),
             tera,
+            providers,
+            signer,
+            metrics,
+        })
+    }
+
+    /// AppData without identity providers, for tests of endpoints which don't need them.
+    /// The pool doesn't connect until a connection is requested
+    #[cfg(test)]
+    pub fn without_providers(env: &Env) -> Result<Self> {
+        let metrics = Metrics::new()?;
+        Ok(Self {
+            pool: Pool::new(mysql::Pool::new_manual(0, 10, env.mysql_options())?, metrics.db_connection_wait.clone()),
+            env: env.clone(),
+            tera: templates()?,
+            providers: HashMap::new(),
+            signer: None,
+            metrics,
         })
     }
 
+    pub fn provider(&self, name: &str) -> Result<&dyn IdentityProvider, Error> {
+        match self.providers.get(name) {
+            Some(p) => Ok(p.as_ref()),
+            None => Err(Error::NotFound("The requested identity provider does not exist")),
+        }
+    }
+
+    pub fn signer(&self) -> Result<&JwtSigner, Error> {
+        match &self.signer {
+            Some(s) => Ok(s),
+            None => Err(Error::NotFound("Authlander is not configured to issue tokens")),
+        }
+    }
+
     pub fn migrate(&self) -> Result<()> {
         let mut conn = self.pool.get_conn()?;
         migrations::migrations::runner().run(&mut conn)?;
+        crate::api_token::migrate_plaintext_tokens(&mut conn)?;
+        if let Some(api_token) = &self.env.bootstrap_admin_token {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            crate::api_token::bootstrap_admin(&mut tx, BOOTSTRAP_ADMIN_NAME, api_token)?;
+            tx.commit()?;
+        }
         Ok(())
     }
 }
\ No newline at end of file
@@ -46,7 +46,13 @@ pub enum Error {
     #[error("Authorization error: {0}")]
     UnauthorizedMsg(&'static str),
     #[error("{0}")]
-    Conflict(&'static str)
+    Conflict(&'static str),
+    #[error("Forbidden: {0}")]
+    Forbidden(&'static str),
+    #[error("Authorization error: The provided ID token is invalid")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("Internal Server Error")]
+    Blocking(#[from] actix_web::error::BlockingError),
 }
 
 impl Error {
@@ -54,6 +60,8 @@ impl Error {
         match self {
             Self::Mysql(e) => warn!("{:?}", e),
             Self::Anyhow(e) => warn!("{:?}", e),
+            Self::Blocking(e) => warn!("{:?}", e),
+            Self::Jwt(e) => warn!("Rejected ID token: {:?}", e),
             _ => {}
         }
     }
@@ -64,10 +72,11 @@ impl ResponseError for Error {
         match self {
             Self::Mysql(_)  | Self::Anyhow(_)
             | Self::SerdeJson(_) | Self::SerdeQs(_) | Self::Tera(_)
-            | Self::Base64(_) | Self::FromUtf8(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            | Self::Base64(_) | Self::FromUtf8(_) | Self::Blocking(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::NotFound(_) => StatusCode::NOT_FOUND,
-            Self::Unauthorized | Self::UnauthorizedMsg(_) => StatusCode::UNAUTHORIZED,
+            Self::Unauthorized | Self::UnauthorizedMsg(_) | Self::Jwt(_) => StatusCode::UNAUTHORIZED,
             Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
         }
     }
@@ -0,0 +1,105 @@
+//! Authlander signs its own ID tokens and access tokens with an RSA key. The public half of the key
+//! is published as a JSON Web Key Set, so downstream services can validate the tokens offline.
+
+use anyhow::Result;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use rsa::RsaPrivateKey;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The `typ` header of ID tokens
+pub const ID_TOKEN_TYPE: &str = "JWT";
+/// The `typ` header of access tokens, as per RFC 9068
+pub const ACCESS_TOKEN_TYPE: &str = "at+jwt";
+
+/// The public key used to verify tokens signed by Authlander, in JWK form
+#[derive(Clone, Serialize)]
+pub struct PublicJwk {
+    kty:    &'static str,
+    alg:    &'static str,
+    #[serde(rename = "use")]
+    usage:  &'static str,
+    kid:    String,
+    n:      String,
+    e:      String,
+}
+
+pub struct JwtSigner {
+    encoding_key:   EncodingKey,
+    jwk:            PublicJwk,
+}
+
+impl JwtSigner {
+    /// Read an RSA private key from a PEM file. Both PKCS#1 and PKCS#8 keys are accepted
+    pub fn from_pem_file(path: &str) -> Result<Self> {
+        let pem = std::fs::read_to_string(path)?;
+        let private_key = match RsaPrivateKey::from_pkcs8_pem(&pem) {
+            Ok(k) => k,
+            Err(_) => RsaPrivateKey::from_pkcs1_pem(&pem)?,
+        };
+
+        let n = private_key.n().to_bytes_be();
+        let e = private_key.e().to_bytes_be();
+
+        // The key ID is derived from the modulus, so it changes whenever the key is replaced
+        let kid = base64::encode_config(&Sha256::digest(&n)[..12], base64::URL_SAFE_NO_PAD);
+
+        Ok(Self {
+            encoding_key:   EncodingKey::from_rsa_pem(pem.as_bytes())?,
+            jwk: PublicJwk {
+                kty:    "RSA",
+                alg:    "RS256",
+                usage:  "sig",
+                kid,
+                n:      base64::encode_config(&n, base64::URL_SAFE_NO_PAD),
+                e:      base64::encode_config(&e, base64::URL_SAFE_NO_PAD),
+            }
+        })
+    }
+
+    /// Sign the claims with RS256, using the provided `typ` header
+    pub fn sign<T: Serialize>(&self, typ: &str, claims: &T) -> Result<String> {
+        let mut header = Header::new(Algorithm::RS256);
+        header.typ = Some(typ.to_string());
+        header.kid = Some(self.jwk.kid.clone());
+
+        Ok(jsonwebtoken::encode(&header, claims, &self.encoding_key)?)
+    }
+
+    pub fn jwk(&self) -> &PublicJwk {
+        &self.jwk
+    }
+}
+
+/// The claims of an ID token issued by Authlander
+#[derive(Serialize)]
+pub struct IdTokenClaims {
+    pub iss:        String,
+    pub sub:        String,
+    /// The name of the API the token was issued for
+    pub aud:        String,
+    pub exp:        i64,
+    pub iat:        i64,
+    pub name:       Option<String>,
+    pub email:      Option<String>,
+    pub picture:    Option<String>,
+    /// The nonce the client provided when starting the login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce:      Option<String>,
+}
+
+/// The claims of an access token issued by Authlander, as per RFC 9068
+#[derive(Serialize)]
+pub struct AccessTokenClaims {
+    pub iss:        String,
+    pub sub:        String,
+    pub aud:        String,
+    pub exp:        i64,
+    pub iat:        i64,
+    pub client_id:  String,
+    /// The user's effective scopes, separated by spaces
+    pub scope:      String,
+}
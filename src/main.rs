@@ -1,14 +1,23 @@
 mod env;
 mod endpoints;
 mod apis;
+mod db;
 mod error;
+mod api_token;
+mod audit;
+mod jwt;
+mod metrics;
+mod sweeper;
+#[cfg(test)]
+mod tests;
 
-use log::{info, debug, error};
-use actix_web::{HttpServer, App};
-use actix_web::middleware::Logger;
+use log::{info, debug, error, warn};
+use actix_web::{web, HttpServer, App};
+use actix_web::middleware::{Logger, NormalizePath, TrailingSlash};
 use std::process::exit;
 use std::sync::Arc;
-use actix_web::middleware::normalize::TrailingSlash;
+use std::time::Instant;
+use actix_web::dev::Service;
 
 #[actix_web::main]
 pub async fn main() -> std::io::Result<()> {
@@ -28,8 +37,12 @@ pub async fn main() -> std::io::Result<()> {
         }
     };
 
+    if env.allow_unauthenticated_user_lookup {
+        warn!("ALLOW_UNAUTHENTICATED_USER_LOOKUP is enabled, /user/exists and /user/scopes can be called without an API token");
+    }
+
     debug!("Creating appdata object");
-    let appdata = match env::AppData::new(&env) {
+    let appdata = match env::AppData::new(&env).await {
         Ok(a) => a,
         Err(e) => {
             error!("Failed to create AppData object: {:?}", e);
@@ -46,22 +59,81 @@ pub async fn main() -> std::io::Result<()> {
     }
 
     let appdata_arc = Arc::new(appdata);
+    actix_web::rt::spawn(sweeper::run(appdata_arc.clone()));
+
+    match appdata_arc.env.metrics_address.clone() {
+        Some(address) => {
+            info!("Serving metrics on {}", address);
+            let metrics_data = appdata_arc.clone();
+            let metrics_server = HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(metrics_data.clone()))
+                    .service(endpoints::metrics::get::get)
+            }).workers(1).bind(address)?.run();
+            actix_web::rt::spawn(metrics_server);
+        },
+        None => info!("METRICS_ADDRESS is not set, not serving metrics"),
+    }
+
     HttpServer::new(move || {
+        let metrics_data = appdata_arc.clone();
         App::new()
             .wrap(actix_cors::Cors::permissive())
             .wrap(Logger::default())
-            .wrap(actix_web::middleware::NormalizePath::new(TrailingSlash::Trim))
-            .data(appdata_arc.clone())
+            .wrap_fn(move |req, srv| {
+                // Requests are labeled by their route rather than their path, so e.g. every user ID doesn't get its own label
+                let method = req.method().to_string();
+                let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let data = metrics_data.clone();
+                let start = Instant::now();
+
+                let fut = srv.call(req);
+                async move {
+                    let res = fut.await?;
+                    data.metrics.observe_request(&method, &route, res.status().as_u16(), start.elapsed().as_secs_f64());
+                    Ok(res)
+                }
+            })
+            .wrap(NormalizePath::new(TrailingSlash::Trim))
+            .app_data(web::Data::new(appdata_arc.clone()))
             .service(endpoints::oauth2::login::login)
             .service(endpoints::oauth2::grant::grant)
+            .service(endpoints::oauth2::token::token)
+            .service(endpoints::oauth2::introspect::introspect)
             .service(endpoints::session::check::check)
             .service(endpoints::session::describe::describe)
+            .service(endpoints::session::logout::logout)
+            .service(endpoints::session::revoke::revoke)
+            .service(endpoints::session::token::token)
             .service(endpoints::token::get::get)
             .service(endpoints::user::scopes::scopes)
             .service(endpoints::user::describe::describe)
             .service(endpoints::user::exists::exists)
             .service(endpoints::user::list::list)
-            .default_service(actix_web::web::route().to(page_404))
+            .service(endpoints::user::activate::activate)
+            .service(endpoints::user::deactivate::deactivate)
+            .service(endpoints::user::delete::delete)
+            .service(endpoints::user::invite::invite)
+            .service(endpoints::scope::add::add)
+            .service(endpoints::scope::remove::remove)
+            .service(endpoints::scope::set::set)
+            .service(endpoints::scope::users::users)
+            .service(endpoints::role::create::create)
+            .service(endpoints::role::delete::delete)
+            .service(endpoints::role::list::list)
+            .service(endpoints::role::set::set)
+            .service(endpoints::role::assign::assign)
+            .service(endpoints::role::unassign::unassign)
+            .service(endpoints::api::create::create)
+            .service(endpoints::api::list::list)
+            .service(endpoints::api::rename::rename)
+            .service(endpoints::api::deactivate::deactivate)
+            .service(endpoints::api::rotate::rotate)
+            .service(endpoints::api::redirect_uris::set)
+            .service(endpoints::audit::list::list)
+            .service(endpoints::well_known::openid_configuration::openid_configuration)
+            .service(endpoints::well_known::jwks::jwks)
+            .default_service(web::route().to(page_404))
     }).bind("0.0.0.0:8080")?.run().await
 }
 
@@ -0,0 +1,87 @@
+//! Prometheus metrics, exposed in the text format on `/metrics` on the separate `METRICS_ADDRESS` listener
+
+use anyhow::Result;
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+/// The error codes a provider may return to /oauth2/grant, or from its token endpoint, which get their own label.
+/// Anything else is counted as `unknown`, so a misbehaving provider can't create an unbounded amount of labels
+const KNOWN_GRANT_ERRORS: [&str; 9] = [
+    "access_denied", "admin_policy_enforced", "org_internal", "disallowed_useragent", "redirect_uri_mismatch",
+    "invalid_request", "invalid_client", "invalid_grant", "unauthorized_client",
+];
+
+pub struct Metrics {
+    registry:                   Registry,
+    http_requests:              IntCounterVec,
+    http_request_duration:      HistogramVec,
+    grant_errors:               IntCounterVec,
+    provider_request_duration:  HistogramVec,
+    pub db_connection_wait:     Histogram,
+    pub active_sessions:        IntGauge,
+    pub pending_states:         IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(Opts::new("http_requests_total", "The amount of HTTP requests handled"), &["method", "route", "status"])?;
+        let http_request_duration = HistogramVec::new(HistogramOpts::new("http_request_duration_seconds", "The time taken to handle HTTP requests"), &["method", "route"])?;
+        let grant_errors = IntCounterVec::new(Opts::new("grant_errors_total", "The amount of logins which failed in /oauth2/grant"), &["error"])?;
+        let provider_request_duration = HistogramVec::new(HistogramOpts::new("identity_provider_request_duration_seconds", "The time taken by calls to identity providers"), &["provider", "call"])?;
+        let db_connection_wait = Histogram::with_opts(HistogramOpts::new("db_connection_wait_seconds", "The time taken to get a connection from the database pool"))?;
+        let active_sessions = IntGauge::new("active_sessions", "The amount of sessions which have not expired")?;
+        let pending_states = IntGauge::new("pending_states", "The amount of logins which were started, but not yet completed")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(grant_errors.clone()))?;
+        registry.register(Box::new(provider_request_duration.clone()))?;
+        registry.register(Box::new(db_connection_wait.clone()))?;
+        registry.register(Box::new(active_sessions.clone()))?;
+        registry.register(Box::new(pending_states.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            grant_errors,
+            provider_request_duration,
+            db_connection_wait,
+            active_sessions,
+            pending_states,
+        })
+    }
+
+    /// Record a handled request. `route` must be the route pattern rather than the path, to keep the amount of labels bounded
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, duration_secs: f64) {
+        self.http_requests.with_label_values(&[method, route, &status.to_string()]).inc();
+        self.http_request_duration.with_label_values(&[method, route]).observe(duration_secs);
+    }
+
+    /// Count a failed login
+    pub fn grant_error(&self, error: &str) {
+        self.grant_errors.with_label_values(&[error]).inc();
+    }
+
+    /// Time a call to an identity provider. The duration is recorded when the timer is dropped
+    pub fn time_provider_call(&self, provider: &str, call: &str) -> HistogramTimer {
+        self.provider_request_duration.with_label_values(&[provider, call]).start_timer()
+    }
+
+    /// Encode all metrics in the Prometheus text format
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// The label under which an error code returned to /oauth2/grant by the identity provider is counted
+pub fn provider_error_label(error: &str) -> &str {
+    if KNOWN_GRANT_ERRORS.contains(&error) {
+        error
+    } else {
+        "unknown"
+    }
+}
@@ -0,0 +1,63 @@
+//! Periodically removes rows from the database which can no longer be used. Without this, abandoned logins
+//! and sessions nobody checks anymore would stay in the database forever.
+
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::web;
+use mysql::{prelude::Queryable, params, TxOpts};
+use log::{debug, warn};
+use crate::audit::{self, AuditEvent};
+use crate::env::AppData;
+use crate::error::Error;
+
+/// Run the sweeper forever, at the interval configured in the environment
+pub async fn run(data: Arc<AppData>) {
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(data.env.sweep_interval_secs));
+    loop {
+        interval.tick().await;
+
+        let data = data.clone();
+        match web::block(move || sweep(&data)).await {
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => warn!("Failed to sweep expired rows: {:?}", e),
+            Err(e) => warn!("Failed to sweep expired rows: {:?}", e),
+        }
+    }
+}
+
+fn sweep(data: &AppData) -> Result<(), Error> {
+    let mut conn = data.pool.get_conn()?;
+    let now = chrono::Utc::now().timestamp();
+
+    conn.exec_drop("DELETE FROM states WHERE created_at <= :created_before", params! {
+        "created_before" => now - data.env.state_ttl_secs
+    })?;
+    let states = conn.affected_rows();
+
+    // A session whose code was never exchanged can not be obtained by anyone
+    conn.exec_drop("DELETE sessions FROM sessions JOIN authorization_codes ON sessions.session_id = authorization_codes.session_id WHERE authorization_codes.expiry <= :now", params! {
+        "now" => now
+    })?;
+    conn.exec_drop("DELETE FROM authorization_codes WHERE expiry <= :now", params! {
+        "now" => now
+    })?;
+    let codes = conn.affected_rows();
+
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    let expired: Vec<(String, Option<String>)> = tx.exec("SELECT user_id,api_name FROM sessions WHERE expiry <= :now OR absolute_expiry <= :now FOR UPDATE", params! {
+        "now" => now
+    })?;
+
+    tx.exec_drop("DELETE FROM sessions WHERE expiry <= :now OR absolute_expiry <= :now", params! {
+        "now" => now
+    })?;
+
+    for (user_id, api_name) in &expired {
+        audit::record(&mut tx, AuditEvent::SessionExpired, Some(user_id), api_name.as_deref(), None)?;
+    }
+    tx.commit()?;
+    let sessions = expired.len();
+
+    debug!("Swept {} expired states, {} expired authorization codes and {} expired sessions", states, codes, sessions);
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! A whole login, from /oauth2/login to a valid session, against the mock issuer and a real database.
+//! The database must be empty or have been migrated by Authlander before. Run with `cargo test -- --ignored`,
+//! with TEST_MYSQL_HOST, TEST_MYSQL_DATABASE, TEST_MYSQL_USERNAME and TEST_MYSQL_PASSWORD set.
+//!
+//! The steps which don't need a database are tested on their own, so they run by default: PKCE and request validation
+//! in `endpoints::oauth2::login`, the redirect page in `endpoints::oauth2`, appending the code in `endpoints::oauth2::grant`,
+//! and the exchange with the issuer in `apis::google_auth`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use actix_web::{test, web, App};
+use mysql::{prelude::Queryable, params};
+use sha2::{Digest, Sha256};
+use crate::endpoints::{oauth2, session};
+use crate::env::AppData;
+use super::mock_issuer::MockIssuer;
+use super::redirect_target;
+
+const RETURN_URI: &str = "https://app.example.com/callback";
+
+fn test_database_var(name: &str) -> String {
+    std::env::var(format!("TEST_{}", name)).unwrap_or_else(|_| panic!("TEST_{} is not set", name))
+}
+
+#[actix_web::test]
+#[ignore = "requires a MySQL database"]
+async fn login_grant_session() {
+    let issuer = MockIssuer::start();
+    let env = super::env(&[
+        ("MYSQL_HOST", &test_database_var("MYSQL_HOST")),
+        ("MYSQL_DATABASE", &test_database_var("MYSQL_DATABASE")),
+        ("MYSQL_USERNAME", &test_database_var("MYSQL_USERNAME")),
+        ("MYSQL_PASSWORD", &test_database_var("MYSQL_PASSWORD")),
+        ("GOOGLE_DISCOVERY_URL", &issuer.discovery_url()),
+    ]);
+
+    let data = AppData::new(&env).await.unwrap();
+    data.migrate().unwrap();
+
+    // Register an API whose logins may return to RETURN_URI
+    let api_name = format!("test-{}", &crate::api_token::generate()[..16]);
+    let api_token = crate::api_token::generate();
+    let hashed = crate::api_token::hash(&api_token);
+    let mut conn = data.pool.get_conn().unwrap();
+    conn.exec_drop("INSERT INTO api_users (active, name, token_prefix, token_salt, token_hash) VALUES (true, :name, :prefix, :salt, :hash)", params! {
+        "name" => &api_name,
+        "prefix" => &hashed.prefix,
+        "salt" => &hashed.salt,
+        "hash" => &hashed.hash
+    }).unwrap();
+    conn.exec_drop("INSERT INTO api_redirect_uris (api_name, uri_prefix) VALUES (:api_name, :uri_prefix)", params! {
+        "api_name" => &api_name,
+        "uri_prefix" => RETURN_URI
+    }).unwrap();
+    drop(conn);
+
+    let app = test::init_service(App::new()
+        .app_data(web::Data::new(Arc::new(data)))
+        .service(oauth2::login::login)
+        .service(oauth2::grant::grant)
+        .service(oauth2::token::token)
+        .service(session::check::check)
+    ).await;
+
+    // An API token is 64 alphanumeric characters, which is a valid PKCE code verifier as well
+    let code_verifier = crate::api_token::generate();
+    let code_challenge = base64::encode_config(Sha256::digest(code_verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+    let return_uri = base64::encode(RETURN_URI);
+
+    let login_query = BTreeMap::from([
+        ("api_name", api_name.as_str()),
+        ("return_uri", return_uri.as_str()),
+        ("code_challenge", code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ]);
+
+    // /oauth2/login sends the user to the issuer
+    let req = test::TestRequest::get().uri(&format!("/oauth2/login?{}", serde_qs::to_string(&login_query).unwrap())).to_request();
+    let authorization_url = redirect_target(&test::call_and_read_body(&app, req).await);
+
+    // The issuer logs the user in, and sends them back to /oauth2/grant
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build().unwrap()
+        .get(&authorization_url)
+        .send().await.unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let grant_path = location.strip_prefix(&env.host).unwrap();
+
+    // /oauth2/grant sends the user back to the API with an authorization code
+    let req = test::TestRequest::get().uri(grant_path).to_request();
+    let return_uri = redirect_target(&test::call_and_read_body(&app, req).await);
+    let code = return_uri.strip_prefix(&format!("{}?code=", RETURN_URI)).unwrap();
+
+    // The API exchanges the code for the session
+    let req = test::TestRequest::post()
+        .uri("/oauth2/token")
+        .insert_header(("authorization", api_token.as_str()))
+        .set_form([("grant_type", "authorization_code"), ("code", code), ("code_verifier", &code_verifier)])
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let session_id = response["session_id"].as_str().unwrap();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/session/check/{}", session_id))
+        .insert_header(("authorization", api_token.as_str()))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["session_valid"], true);
+    assert_eq!(response["active"], true);
+}
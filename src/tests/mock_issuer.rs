@@ -0,0 +1,140 @@
+//! A minimal OpenID Connect issuer, serving its discovery document, an authorization endpoint, a token endpoint
+//! and its key set from a local server. Users are logged in without asking, and ID tokens are signed with the test key.
+
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+use actix_web::{get, post, web, App, HttpResponse, HttpServer};
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::json;
+
+pub const CLIENT_ID: &str = "mock-client";
+/// The subject of the user every login is for
+pub const SUB: &str = "1234";
+pub const EMAIL: &str = "user@example.com";
+
+pub struct MockIssuer {
+    state:  web::Data<IssuerState>,
+}
+
+struct IssuerState {
+    url:    String,
+    /// The nonce every outstanding authorization code was issued for
+    codes:  Mutex<HashMap<String, String>>,
+}
+
+impl IssuerState {
+    fn issue_code(&self, nonce: &str) -> String {
+        let code: String = rand::thread_rng().sample_iter(rand::distributions::Alphanumeric).take(32).map(char::from).collect();
+        self.codes.lock().unwrap().insert(code.clone(), nonce.to_string());
+        code
+    }
+}
+
+impl MockIssuer {
+    /// Start the issuer on a random local port. Must be called from within an actix runtime
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = web::Data::new(IssuerState {
+            url:    format!("http://{}", listener.local_addr().unwrap()),
+            codes:  Mutex::new(HashMap::new()),
+        });
+
+        let server_state = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_state.clone())
+                .service(discovery)
+                .service(authorize)
+                .service(token)
+                .service(jwks)
+        })
+            .workers(1)
+            .disable_signals()
+            .listen(listener).unwrap()
+            .run();
+        actix_web::rt::spawn(server);
+
+        Self { state }
+    }
+
+    pub fn discovery_url(&self) -> String {
+        format!("{}/.well-known/openid-configuration", self.state.url)
+    }
+}
+
+#[get("/.well-known/openid-configuration")]
+async fn discovery(state: web::Data<IssuerState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": state.url,
+        "authorization_endpoint": format!("{}/authorize", state.url),
+        "token_endpoint": format!("{}/token", state.url),
+        "jwks_uri": format!("{}/jwks", state.url),
+    }))
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    redirect_uri:   String,
+    state:          String,
+    nonce:          String,
+}
+
+/// Send the user straight back with a code
+#[get("/authorize")]
+async fn authorize(state: web::Data<IssuerState>, query: web::Query<AuthorizeQuery>) -> HttpResponse {
+    let code = state.issue_code(&query.nonce);
+    HttpResponse::Found()
+        .insert_header(("location", format!("{}?code={}&state={}", query.redirect_uri, code, query.state)))
+        .finish()
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    client_id:  String,
+    code:       String,
+}
+
+/// The Google provider sends a JSON body, other providers send a form
+#[post("/token")]
+async fn token(state: web::Data<IssuerState>, body: web::Bytes) -> HttpResponse {
+    let request: TokenRequest = match serde_json::from_slice(&body) {
+        Ok(r) => r,
+        Err(_) => serde_qs::from_bytes(&body).unwrap(),
+    };
+
+    let nonce = match state.codes.lock().unwrap().remove(&request.code) {
+        Some(n) => n,
+        None => return HttpResponse::BadRequest().json(json!({
+            "error": "invalid_grant",
+            "error_description": "The code is invalid or has already been used",
+        })),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let id_token = super::signer().sign("JWT", &json!({
+        "iss": state.url,
+        "sub": SUB,
+        "aud": request.client_id,
+        "iat": now,
+        "exp": now + 3600,
+        "nonce": nonce,
+        "name": "Test User",
+        "email": EMAIL,
+        "email_verified": true,
+    })).unwrap();
+
+    HttpResponse::Ok().json(json!({
+        "access_token": "mock-access-token",
+        "expires_in": 3600,
+        "refresh_token": "mock-refresh-token",
+        "id_token": id_token,
+    }))
+}
+
+#[get("/jwks")]
+async fn jwks() -> HttpResponse {
+    let keys = std::fs::read_to_string(super::JWKS.trim_start_matches("file://")).unwrap();
+    HttpResponse::Ok().content_type("application/json").body(keys)
+}
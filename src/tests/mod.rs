@@ -0,0 +1,63 @@
+//! Helpers shared by the tests
+
+pub mod mock_issuer;
+mod login_flow;
+
+use std::sync::Arc;
+use actix_web::{test, web, App};
+use actix_web::dev::HttpServiceFactory;
+use actix_web::http::StatusCode;
+use crate::env::{AppData, Env};
+use crate::jwt::JwtSigner;
+
+/// A key which is only used in tests, so tokens can be signed like an identity provider would
+pub const SIGNING_KEY: &str = "testdata/jwt_signing_key.pem";
+/// The public half of `SIGNING_KEY`
+pub const JWKS: &str = "file://testdata/jwks.json";
+
+pub fn signer() -> JwtSigner {
+    JwtSigner::from_pem_file(SIGNING_KEY).unwrap()
+}
+
+/// Read the environment from the provided variables, on top of placeholders for the required ones
+pub fn env(vars: &[(&str, &str)]) -> Env {
+    let defaults = [
+        ("MYSQL_HOST", "127.0.0.1"),
+        ("MYSQL_DATABASE", "authlander"),
+        ("MYSQL_USERNAME", "authlander"),
+        ("MYSQL_PASSWORD", "authlander"),
+        ("GOOGLE_CLIENT_ID", mock_issuer::CLIENT_ID),
+        ("GOOGLE_CLIENT_SECRET", "mock-secret"),
+        ("HOST", "http://authlander.test"),
+    ];
+
+    let vars = defaults.iter()
+        .filter(|(name, _)| !vars.iter().any(|(n, _)| n == name))
+        .chain(vars)
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+    envy::from_iter(vars).unwrap()
+}
+
+/// AppData without a database or identity providers, for endpoints which fail before needing either
+pub fn app_data(vars: &[(&str, &str)]) -> web::Data<Arc<AppData>> {
+    web::Data::new(Arc::new(AppData::without_providers(&env(vars)).unwrap()))
+}
+
+/// GET the URI from the service without an API token or a database. Returns the response status, and the amount of
+/// database connections the handler asked for, which shows whether it got past authentication to the database
+pub async fn get_without_database<F: HttpServiceFactory + 'static>(service: F, uri: &str, vars: &[(&str, &str)]) -> (StatusCode, u64) {
+    let data = app_data(vars);
+    let app = test::init_service(App::new().app_data(data.clone()).service(service)).await;
+    let response = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+
+    (response.status(), data.metrics.db_connection_wait.get_sample_count())
+}
+
+/// Get the URI the redirect template sends the browser to
+pub fn redirect_target(body: &[u8]) -> String {
+    let body = std::str::from_utf8(body).unwrap();
+    let start = body.find("window.location.href = ").unwrap() + "window.location.href = ".len();
+    let end = start + body[start..].find(";\n").unwrap();
+    serde_json::from_str(&body[start..end]).unwrap()
+}